@@ -0,0 +1,260 @@
+use std::collections::BTreeSet;
+use nannou::image::{Rgba, RgbaImage};
+use crate::{
+    image::{
+        hypsometric, with_sea, VisualizationMode,
+        charts::fill_rect,
+        contours::draw_line,
+        overlays::{blend, Overlays},
+        palette::Palette,
+        text::{draw_text, text_width}
+    },
+    map::{
+        fauna::Species,
+        hazards::Hazard,
+        tile::Field,
+        world::World
+    },
+    utils::helpers::scale_f64_to_u8
+};
+
+const LABEL_SIZE: f32 = 12.0;
+const TITLE_SIZE: f32 = 14.0;
+/// Annotations are drawn at their base size on images whose shorter side is
+/// this long or less, and grow with larger images.
+const REFERENCE_SIDE: f32 = 500.0;
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+const PANEL_ALPHA: f64 = 0.8;
+const INK: Rgba<u8> = Rgba([30, 30, 30, 255]);
+const PAPER: [u8; 3] = [255, 255, 255];
+const COLORBAR_WIDTH: f32 = 160.0;
+const COLORBAR_STEPS: usize = 128;
+const COMPASS_RADIUS: f32 = 16.0;
+
+enum Legend {
+    Swatches(Vec<(String, [u8; 3])>),
+    /// Colors sampled evenly from -1.0 to 1.0, and labeled values along the bar
+    Colorbar(Vec<[u8; 3]>, Vec<(f64, String)>),
+}
+
+fn ramp(color: impl Fn(f64) -> [u8; 3]) -> Vec<[u8; 3]> {
+    (0..COLORBAR_STEPS).map(|i| color(i as f64 / (COLORBAR_STEPS - 1) as f64 * 2.0 - 1.0)).collect()
+}
+
+/// Translucent backing with a thin border, so annotations read over any map.
+fn panel(img: &mut RgbaImage, x: i32, y: i32, w: i32, h: i32) {
+    let (x0, y0) = (x.max(0) as u32, y.max(0) as u32);
+    let (x1, y1) = (((x + w).max(0) as u32).min(img.width()), ((y + h).max(0) as u32).min(img.height()));
+    for py in y0..y1 {
+        for px in x0..x1 {
+            blend(img.get_pixel_mut(px, py), PAPER, PANEL_ALPHA);
+        }
+    }
+    if x1 <= x0 || y1 <= y0 { return; }
+    fill_rect(img, x0, y0, x1 - x0, 1, INK);
+    fill_rect(img, x0, y1 - 1, x1 - x0, 1, INK);
+    fill_rect(img, x0, y0, 1, y1 - y0, INK);
+    fill_rect(img, x1 - 1, y0, 1, y1 - y0, INK);
+}
+
+fn fill_triangle(img: &mut RgbaImage, [a, b, c]: [(f64, f64); 3], color: [u8; 3]) {
+    let edge = |p: (f64, f64), q: (f64, f64), x: f64, y: f64| (q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0);
+    let (left, right) = (a.0.min(b.0).min(c.0).max(0.0), a.0.max(b.0).max(c.0).min(img.width() as f64 - 1.0));
+    let (top, bottom) = (a.1.min(b.1).min(c.1).max(0.0), a.1.max(b.1).max(c.1).min(img.height() as f64 - 1.0));
+    for y in top as u32..=bottom as u32 {
+        for x in left as u32..=right as u32 {
+            let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+            let sides = [edge(a, b, px, py), edge(b, c, px, py), edge(c, a, px, py)];
+            if sides.iter().all(|s| *s >= 0.0) || sides.iter().all(|s| *s <= 0.0) {
+                blend(img.get_pixel_mut(x, y), color, 1.0);
+            }
+        }
+    }
+}
+
+/// Largest length of 1, 2 or 5 times a power of ten that fits in `target`.
+fn round_length(target: f64) -> f64 {
+    let magnitude = 10f64.powf(target.log10().floor());
+    [5.0, 2.0, 1.0].into_iter()
+        .map(|m| m * magnitude)
+        .find(|length| *length <= target)
+        .unwrap_or(magnitude)
+}
+
+impl World {
+    /// What the colors of `mode` mean, or nothing for modes with no fixed key.
+    fn legend(&self, mode: &VisualizationMode, palette: &Palette) -> Option<Legend> {
+        let sea_level = self.params.sea_level;
+        let field_ticks = || vec![(-1.0, "-1".to_string()), (0.0, "0".to_string()), (1.0, "1".to_string())];
+        let altitude_ticks = || vec![(-1.0, "-1".to_string()), (sea_level, "sea".to_string()), (1.0, "1".to_string())];
+
+        let legend = match mode {
+            VisualizationMode::Biome => {
+                let present = self.tiles.iter().map(|tile| tile.biome).collect::<BTreeSet<_>>();
+                Legend::Swatches(present.into_iter().map(|biome| (biome.to_string(), palette.biome(biome))).collect())
+            },
+            VisualizationMode::Altitude => Legend::Colorbar(ramp(|v| palette.altitude(v, sea_level)), altitude_ticks()),
+            VisualizationMode::AltitudeWithSea => Legend::Colorbar(ramp(|v| with_sea(v, sea_level)), altitude_ticks()),
+            VisualizationMode::Relief => Legend::Colorbar(ramp(|v| hypsometric(v, sea_level)), altitude_ticks()),
+            VisualizationMode::Temperature => Legend::Colorbar(ramp(|v| palette.field(Field::Temperature, v)), field_ticks()),
+            VisualizationMode::Rainfall => Legend::Colorbar(ramp(|v| palette.field(Field::Rainfall, v)), field_ticks()),
+            VisualizationMode::EquatorDistance => Legend::Colorbar(
+                ramp(|v| [scale_f64_to_u8(v); 3]),
+                vec![(-1.0, "pole".to_string()), (0.0, "equator".to_string())]
+            ),
+            VisualizationMode::Fauna => Legend::Swatches(
+                Species::ALL.iter().map(|species| (species.to_string(), species.color())).collect()
+            ),
+            VisualizationMode::Hazards => Legend::Swatches(
+                Hazard::ALL.iter().map(|hazard| (hazard.to_string(), hazard.color()))
+                    .chain([("recently struck".to_string(), PAPER)])
+                    .collect()
+            ),
+            _ => return None
+        };
+        Some(legend)
+    }
+
+    /// Composites a legend for `mode`, the seed and main parameters, a scale
+    /// bar and a north arrow onto `img`, rendered at `scale` pixels per tile.
+    pub fn annotate(&self, img: &mut RgbaImage, mode: &VisualizationMode, overlays: &Overlays, scale: u32) {
+        let unit = (img.width().min(img.height()) as f32 / REFERENCE_SIDE).max(1.0);
+        self.draw_summary(img, mode, unit);
+        if let Some(legend) = self.legend(mode, &overlays.palette) {
+            draw_legend(img, &mode.to_string(), &legend, unit);
+        }
+        self.draw_scale_bar(img, scale, unit);
+        draw_compass(img, unit);
+    }
+
+    /// Seed, mode, size and the parameters that most change a map's look, top left.
+    fn draw_summary(&self, img: &mut RgbaImage, mode: &VisualizationMode, unit: f32) {
+        let mut lines = vec![
+            format!("seed {}", self.seed),
+            format!("{}   {}x{} tiles", mode, self.width, self.height),
+            format!(
+                "sea level {:.2}   heat scaling {:.2}   ecotones {:.2}",
+                self.params.sea_level, self.params.global_heat_scaling, self.params.ecotone_width
+            ),
+        ];
+        if self.viewport.scale > 1.0 {
+            lines.push(format!(
+                "zoom {}x at ({:.0}, {:.0})",
+                self.viewport.scale, self.viewport.x, self.viewport.y
+            ));
+        }
+        if self.year > 0 {
+            lines.push(format!("year {}   {} hazard events", self.year, self.events.len()));
+        }
+
+        let (size, line_h, margin, padding) = (LABEL_SIZE * unit, LABEL_SIZE * unit * 1.4, MARGIN * unit, PADDING * unit);
+        let w = lines.iter().map(|line| text_width(line, size)).fold(0.0, f32::max) + 2.0 * padding;
+        let h = lines.len() as f32 * line_h + 2.0 * padding;
+        panel(img, margin as i32, margin as i32, w as i32, h as i32);
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(img, line, (margin + padding) as i32, (margin + padding + i as f32 * line_h) as i32, size, INK);
+        }
+    }
+
+    /// Bar of a round length in world units, bottom right. Unzoomed, a world
+    /// unit is one tile.
+    fn draw_scale_bar(&self, img: &mut RgbaImage, scale: u32, unit: f32) {
+        let pixels_per_unit = self.viewport.scale * scale as f64;
+        let length = round_length(img.width() as f64 / 5.0 / pixels_per_unit);
+        let label = format!("{} units", length);
+
+        let (size, margin, padding) = (LABEL_SIZE * unit, MARGIN * unit, PADDING * unit);
+        let bar_w = (length * pixels_per_unit) as f32;
+        let bar_h = (6.0 * unit).round();
+        let w = bar_w.max(text_width(&label, size)) + 2.0 * padding;
+        let h = bar_h + size * 1.4 + 2.0 * padding;
+        let (x, y) = (img.width() as f32 - margin - w, img.height() as f32 - margin - h);
+        panel(img, x as i32, y as i32, w as i32, h as i32);
+
+        let (bar_x, bar_y) = (x + (w - bar_w) / 2.0, y + padding);
+        fill_rect(img, bar_x as u32, bar_y as u32, bar_w as u32, bar_h as u32, INK);
+        for segment in (1..4).step_by(2) {
+            let sx = bar_x + bar_w * segment as f32 / 4.0;
+            let (tick_w, tick_h) = ((bar_w / 4.0) as u32, bar_h as u32);
+            fill_rect(img, sx as u32 + 1, bar_y as u32 + 1, tick_w.saturating_sub(1), tick_h.saturating_sub(2), Rgba([255, 255, 255, 255]));
+        }
+        let label_x = x + (w - text_width(&label, size)) / 2.0;
+        draw_text(img, &label, label_x as i32, (bar_y + bar_h + 2.0 * unit) as i32, size, INK);
+    }
+}
+
+/// Swatches or a colorbar under the mode's name, bottom left.
+fn draw_legend(img: &mut RgbaImage, title: &str, legend: &Legend, unit: f32) {
+    let (size, title_size) = (LABEL_SIZE * unit, TITLE_SIZE * unit);
+    let (line_h, margin, padding) = (size * 1.4, MARGIN * unit, PADDING * unit);
+    let title_h = title_size * 1.5;
+
+    match legend {
+        Legend::Swatches(entries) => {
+            let swatch = size.round();
+            let label_w = entries.iter().map(|(label, _)| text_width(label, size)).fold(0.0, f32::max);
+            let w = (swatch + padding + label_w).max(text_width(title, title_size)) + 2.0 * padding;
+            let h = title_h + entries.len() as f32 * line_h + 2.0 * padding;
+            let (x, y) = (margin, img.height() as f32 - margin - h);
+            panel(img, x as i32, y as i32, w as i32, h as i32);
+            draw_text(img, title, (x + padding) as i32, (y + padding) as i32, title_size, INK);
+
+            for (i, (label, color)) in entries.iter().enumerate() {
+                let row = y + padding + title_h + i as f32 * line_h;
+                let [r, g, b] = *color;
+                fill_rect(img, (x + padding) as u32, row as u32, swatch as u32, swatch as u32, INK);
+                fill_rect(img, (x + padding) as u32 + 1, row as u32 + 1, swatch as u32 - 2, swatch as u32 - 2, Rgba([r, g, b, 255]));
+                draw_text(img, label, (x + 2.0 * padding + swatch) as i32, row as i32, size, INK);
+            }
+        },
+        Legend::Colorbar(colors, ticks) => {
+            let bar_w = COLORBAR_WIDTH * unit;
+            let bar_h = size.round();
+            // Room for the end labels, which are centered on the bar's ends
+            let overhang = ticks.iter().map(|(_, label)| text_width(label, size)).fold(0.0, f32::max) / 2.0;
+            let w = (bar_w + 2.0 * overhang).max(text_width(title, title_size)) + 2.0 * padding;
+            let h = title_h + bar_h + 3.0 * unit + line_h + 2.0 * padding;
+            let (x, y) = (margin, img.height() as f32 - margin - h);
+            panel(img, x as i32, y as i32, w as i32, h as i32);
+            draw_text(img, title, (x + padding) as i32, (y + padding) as i32, title_size, INK);
+
+            let (bar_x, bar_y) = (x + (w - bar_w) / 2.0, y + padding + title_h);
+            fill_rect(img, bar_x as u32 - 1, bar_y as u32 - 1, bar_w as u32 + 2, bar_h as u32 + 2, INK);
+            for px in 0..bar_w as u32 {
+                let i = (px as f32 / bar_w * colors.len() as f32) as usize;
+                let [r, g, b] = colors[i.min(colors.len() - 1)];
+                fill_rect(img, bar_x as u32 + px, bar_y as u32, 1, bar_h as u32, Rgba([r, g, b, 255]));
+            }
+            for (value, label) in ticks {
+                let tx = bar_x + ((value.clamp(-1.0, 1.0) + 1.0) / 2.0) as f32 * bar_w;
+                fill_rect(img, tx as u32, (bar_y + bar_h) as u32, 1, (3.0 * unit) as u32, INK);
+                let label_x = tx - text_width(label, size) / 2.0;
+                draw_text(img, label, label_x as i32, (bar_y + bar_h + 3.0 * unit) as i32, size, INK);
+            }
+        },
+    }
+}
+
+/// North arrow, top right. Rows grow southwards, so north is always up.
+fn draw_compass(img: &mut RgbaImage, unit: f32) {
+    let (size, margin, padding) = (LABEL_SIZE * unit, MARGIN * unit, PADDING * unit);
+    let radius = COMPASS_RADIUS * unit;
+    let w = 2.0 * radius / 1.5 + 2.0 * padding;
+    let h = size * 1.2 + 2.0 * radius + 2.0 * padding;
+    let (x, y) = (img.width() as f32 - margin - w, margin);
+    panel(img, x as i32, y as i32, w as i32, h as i32);
+
+    let label_x = x + (w - text_width("N", size)) / 2.0;
+    draw_text(img, "N", label_x as i32, (y + padding) as i32, size, INK);
+
+    let (cx, cy) = ((x + w / 2.0) as f64, (y + padding + size * 1.2 + radius) as f64);
+    let (length, half) = (radius as f64, radius as f64 / 3.0);
+    let (north, south, west, east) = ((cx, cy - length), (cx, cy + length), (cx - half, cy), (cx + half, cy));
+    let [r, g, b, _] = INK.0;
+    fill_triangle(img, [north, west, east], [r, g, b]);
+    fill_triangle(img, [south, east, west], PAPER);
+    for (from, to) in [(north, west), (west, south), (south, east), (east, north)] {
+        draw_line(img, from, to, [r, g, b], 1.0);
+    }
+}
@@ -0,0 +1,74 @@
+use nannou::image::{Rgba, RgbaImage};
+use crate::{
+    image::{palette::Palette, text::draw_text},
+    map::stats::WorldStats
+};
+
+const WIDTH: u32 = 900;
+const MARGIN: u32 = 20;
+const PANEL_HEIGHT: u32 = 180;
+const TITLE_SIZE: f32 = 16.0;
+const LABEL_SIZE: f32 = 12.0;
+const BAR_HEIGHT: u32 = 16;
+const BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const INK: Rgba<u8> = Rgba([30, 30, 30, 255]);
+const FIELD_COLOR: Rgba<u8> = Rgba([70, 110, 170, 255]);
+
+pub fn fill_rect(img: &mut RgbaImage, x: u32, y: u32, w: u32, h: u32, color: Rgba<u8>) {
+    for px in x..(x + w).min(img.width()) {
+        for py in y..(y + h).min(img.height()) {
+            img.put_pixel(px, py, color);
+        }
+    }
+}
+
+/// Renders one histogram per field followed by a bar chart of biome shares,
+/// with biomes in their `palette` colors.
+pub fn render_stats(stats: &WorldStats, palette: &Palette) -> RgbaImage {
+    let biome_height = MARGIN * 2 + 24 + stats.biomes.len() as u32 * (BAR_HEIGHT + 4);
+    let height = PANEL_HEIGHT * stats.fields.len() as u32 + biome_height;
+    let mut img = RgbaImage::from_pixel(WIDTH, height, BACKGROUND);
+    let plot_w = WIDTH - 2 * MARGIN;
+
+    for (i, field) in stats.fields.iter().enumerate() {
+        let top = i as u32 * PANEL_HEIGHT + MARGIN;
+        let title = format!(
+            "{}   min {:.3}   p50 {:.3}   mean {:.3}   max {:.3}",
+            field.field, field.min, field.percentiles["p50"], field.mean, field.max
+        );
+        draw_text(&mut img, &title, MARGIN as i32, top as i32, TITLE_SIZE, INK);
+
+        let plot_top = top + 24;
+        let plot_h = PANEL_HEIGHT - 24 - 2 * MARGIN;
+        let peak = *field.histogram.iter().max().unwrap_or(&1).max(&1) as f64;
+        let bar_w = plot_w / field.histogram.len() as u32;
+        for (bin, count) in field.histogram.iter().enumerate() {
+            let h = ((*count as f64 / peak) * plot_h as f64).round() as u32;
+            let x = MARGIN + bin as u32 * bar_w;
+            fill_rect(&mut img, x, plot_top + plot_h - h, bar_w.saturating_sub(1).max(1), h, FIELD_COLOR);
+        }
+        fill_rect(&mut img, MARGIN, plot_top + plot_h, plot_w, 1, INK);
+        draw_text(&mut img, &format!("{:.2}", field.min), MARGIN as i32, (plot_top + plot_h + 2) as i32, LABEL_SIZE, INK);
+        draw_text(&mut img, &format!("{:.2}", field.max), (WIDTH - MARGIN - 30) as i32, (plot_top + plot_h + 2) as i32, LABEL_SIZE, INK);
+    }
+
+    let top = stats.fields.len() as u32 * PANEL_HEIGHT + MARGIN;
+    let title = format!(
+        "biomes   land {:.1}%   coastline {}   landmasses {}",
+        stats.land_ratio * 100.0, stats.coastline_length, stats.landmass_count
+    );
+    draw_text(&mut img, &title, MARGIN as i32, top as i32, TITLE_SIZE, INK);
+
+    let label_w = 100;
+    for (i, (biome, share)) in stats.biomes.iter().enumerate() {
+        let y = top + 24 + i as u32 * (BAR_HEIGHT + 4);
+        let [r, g, b] = palette.biome(*biome);
+        let w = (share * (plot_w - label_w - 60) as f64).round() as u32;
+        draw_text(&mut img, &biome.to_string(), MARGIN as i32, y as i32, LABEL_SIZE, INK);
+        fill_rect(&mut img, MARGIN + label_w - 1, y - 1, w + 2, BAR_HEIGHT + 2, INK);
+        fill_rect(&mut img, MARGIN + label_w, y, w.max(1), BAR_HEIGHT, Rgba([r, g, b, 255]));
+        draw_text(&mut img, &format!("{:.1}%", share * 100.0), (MARGIN + label_w + w + 6) as i32, y as i32, LABEL_SIZE, INK);
+    }
+
+    img
+}
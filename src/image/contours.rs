@@ -0,0 +1,179 @@
+use nannou::image::RgbaImage;
+use crate::{
+    image::overlays::blend,
+    map::{tile::Field, world::World},
+    utils::cli::Args
+};
+
+/// Every this many levels a line is drawn stronger, as an index contour.
+const INDEX_EVERY: i64 = 5;
+const LINE_ALPHA: f64 = 0.55;
+const INDEX_ALPHA: f64 = 0.9;
+
+/// Lines of equal value every `interval` units of `field`.
+#[derive(Clone, Copy, Debug)]
+pub struct Isolines {
+    pub field: Field,
+    pub interval: f64,
+}
+
+impl Isolines {
+    /// Parses `--contour field=interval` arguments, defaulting to altitude every 0.1.
+    pub fn from_args(args: &Args) -> Result<Vec<Isolines>, String> {
+        if args.contour.is_empty() {
+            return Ok(vec![Isolines { field: Field::Altitude, interval: 0.1 }]);
+        }
+        args.contour.iter().map(|text| {
+            let (name, interval) = text.split_once('=')
+                .ok_or(format!("Contour '{}' should look like 'altitude=0.1'.", text))?;
+            let field = Field::ALL.into_iter()
+                .find(|field| field.to_string() == name)
+                .ok_or(format!("Unknown contour field '{}'.", name))?;
+            let interval = interval.parse::<f64>()
+                .ok()
+                .filter(|interval| *interval > 0.0)
+                .ok_or(format!("Contour interval '{}' should be a positive number.", interval))?;
+            Ok(Isolines { field, interval })
+        }).collect()
+    }
+
+    fn color(&self) -> [u8; 3] {
+        match self.field {
+            Field::Altitude => [60, 40, 20],
+            Field::Temperature => [200, 30, 30],
+            Field::Rainfall => [20, 60, 200],
+        }
+    }
+}
+
+/// Crossing of `level` along the edge from `p` to `q`, as (x, y, value) corners.
+fn crossing(p: (f64, f64, f64), q: (f64, f64, f64), level: f64) -> Option<(f64, f64)> {
+    if (p.2 >= level) == (q.2 >= level) { return None; }
+    let t = (level - p.2) / (q.2 - p.2);
+    Some((p.0 + (q.0 - p.0) * t, p.1 + (q.1 - p.1) * t))
+}
+
+/// Plots a line with Xiaolin Wu's algorithm, blending `color` by pixel coverage.
+/// The end pixels are only covered by the part of the line inside them, so
+/// segments sharing an endpoint join without a darker dot.
+pub fn draw_line(img: &mut RgbaImage, from: (f64, f64), to: (f64, f64), color: [u8; 3], alpha: f64) {
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    let ((mut x0, mut y0), (mut x1, mut y1)) = (from, to);
+    let steep = (y1 - y0).abs() > (x1 - x0).abs();
+    if steep { (x0, y0, x1, y1) = (y0, x0, y1, x1); }
+    if x0 > x1 { (x0, y0, x1, y1) = (x1, y1, x0, y0); }
+    let gradient = if x1 == x0 { 1.0 } else { (y1 - y0) / (x1 - x0) };
+
+    // Splits `coverage` of column `x` between the two rows around `y`
+    let mut plot = |x: i64, y: f64, coverage: f64| {
+        let (row, fraction) = (y.floor() as i64, y - y.floor());
+        for (row, share) in [(row, 1.0 - fraction), (row + 1, fraction)] {
+            let (px, py) = if steep { (row, x) } else { (x, row) };
+            if px >= 0 && py >= 0 && px < width && py < height {
+                blend(img.get_pixel_mut(px as u32, py as u32), color, alpha * coverage * share);
+            }
+        }
+    };
+
+    let (first, last) = (x0.round(), x1.round());
+    if first == last {
+        plot(first as i64, (y0 + y1) / 2.0, x1 - x0);
+        return;
+    }
+    // Share of each end pixel's column the line actually spans
+    let first_gap = first + 0.5 - x0;
+    let last_gap = x1 - (last - 0.5);
+    plot(first as i64, y0 + gradient * (first - x0), first_gap);
+    plot(last as i64, y0 + gradient * (last - x0), last_gap);
+
+    let mut y = y0 + gradient * (first + 1.0 - x0);
+    for x in first as i64 + 1..last as i64 {
+        plot(x, y, 1.0);
+        y += gradient;
+    }
+}
+
+impl World {
+    /// Traces isolines with marching squares between tile centers and draws
+    /// them anti-aliased onto `img`, which is `scale` times the world size.
+    pub fn draw_contours(&self, img: &mut RgbaImage, isolines: &[Isolines], scale: f64) {
+        for isoline in isolines {
+            let value = |x: usize, y: usize| self.tiles[x + y * self.width].field(isoline.field);
+            let color = isoline.color();
+
+            for y in 0..self.height.saturating_sub(1) {
+                for x in 0..self.width.saturating_sub(1) {
+                    let corner = |dx: usize, dy: usize| (
+                        (x + dx) as f64 + 0.5,
+                        (y + dy) as f64 + 0.5,
+                        value(x + dx, y + dy)
+                    );
+                    let (a, b, c, d) = (corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 1));
+                    let low = a.2.min(b.2).min(c.2).min(d.2);
+                    let high = a.2.max(b.2).max(c.2).max(d.2);
+
+                    for k in (low / isoline.interval).ceil() as i64..=(high / isoline.interval).floor() as i64 {
+                        let level = k as f64 * isoline.interval;
+                        let (top, right, bottom, left) = (
+                            crossing(a, b, level),
+                            crossing(b, c, level),
+                            crossing(c, d, level),
+                            crossing(d, a, level)
+                        );
+                        let segments = match (top, right, bottom, left) {
+                            (Some(top), Some(right), Some(bottom), Some(left)) => {
+                                // Saddle: the cell center decides which corners connect
+                                let center = (a.2 + b.2 + c.2 + d.2) / 4.0 >= level;
+                                if (a.2 >= level) != center { vec![(top, left), (right, bottom)] }
+                                else { vec![(top, right), (bottom, left)] }
+                            },
+                            _ => {
+                                let points = [top, right, bottom, left].into_iter().flatten().collect::<Vec<(f64, f64)>>();
+                                if points.len() == 2 { vec![(points[0], points[1])] } else { Vec::new() }
+                            }
+                        };
+                        let alpha = if k % INDEX_EVERY == 0 { INDEX_ALPHA } else { LINE_ALPHA };
+                        for (from, to) in segments {
+                            draw_line(img, (from.0 * scale, from.1 * scale), (to.0 * scale, to.1 * scale), color, alpha);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nannou::image::Rgba;
+    use super::*;
+
+    fn row(from: (f64, f64), joints: &[(f64, f64)], to: (f64, f64)) -> Vec<u8> {
+        let mut img = RgbaImage::from_pixel(12, 3, Rgba([255, 255, 255, 255]));
+        let points = std::iter::once(from).chain(joints.iter().copied()).chain(std::iter::once(to)).collect::<Vec<_>>();
+        points.windows(2).for_each(|pair| draw_line(&mut img, pair[0], pair[1], [0, 0, 0], 0.5));
+        (0..12).map(|x| img.get_pixel(x, 1)[0]).collect()
+    }
+
+    /// Two partial blends come out slightly lighter than one full blend, but
+    /// nowhere near the dark bead a doubly covered joint leaves.
+    #[test]
+    fn joined_segments_match_a_single_line() {
+        let single = row((1.0, 1.0), &[], (10.0, 1.0));
+        for joints in [vec![(4.3, 1.0)], vec![(3.0, 1.0), (6.5, 1.0), (7.8, 1.0)]] {
+            let joined = row((1.0, 1.0), &joints, (10.0, 1.0));
+            for (x, (a, b)) in single.iter().zip(&joined).enumerate() {
+                assert!((*a as i32 - *b as i32).abs() <= 20, "column {}: {} vs {}", x, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn end_pixels_are_partly_covered() {
+        let line = row((1.0, 1.0), &[], (10.0, 1.0));
+        assert!(line[1] > line[2], "the first pixel is only half covered");
+        assert!(line[10] > line[9], "the last pixel is only half covered");
+        assert_eq!(line[0], 255);
+        assert_eq!(line[11], 255);
+    }
+}
@@ -0,0 +1,331 @@
+use std::{
+    str::FromStr,
+    path::PathBuf,
+    fmt::{Formatter, Result, Display}
+};
+use chrono::Local;
+use nannou::image::{
+    imageops::{resize, FilterType},
+    save_buffer, Rgba, ColorType::Rgba8, RgbaImage, DynamicImage
+};
+use crate::{
+    image::{overlays::Overlays, palette::Palette},
+    map::{
+        world::World,
+        fauna::Species,
+        hazards::Hazard,
+        tile::{Tile, Biome, Field}
+    },
+    utils::helpers::scale_f64_to_u8
+};
+
+pub mod annotations;
+pub mod charts;
+pub mod contours;
+pub mod overlays;
+pub mod palette;
+pub mod relief;
+pub mod text;
+
+const DATE_FORMAT: &str = "%y%m%d-%Hh%M";
+
+#[allow(dead_code)]
+pub enum VisualizationMode {
+    Biome,
+    Altitude,
+    AltitudeWithSea,
+    Temperature,
+    Rainfall,
+    Vegetation,
+    Hardness,
+    Sunlight,
+    Debug,
+    EquatorDistance,
+    Fauna,
+    Hazards,
+    Relief,
+}
+
+impl Display for VisualizationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            VisualizationMode::Biome => write!(f, "biome"),
+            VisualizationMode::Altitude => write!(f, "altitude"),
+            VisualizationMode::AltitudeWithSea => write!(f, "altitude (with sea)"),
+            VisualizationMode::Temperature => write!(f, "temperature"),
+            VisualizationMode::Rainfall => write!(f, "rainfall"),
+            VisualizationMode::Vegetation => write!(f, "vegetation"),
+            VisualizationMode::Hardness => write!(f, "hardness"),
+            VisualizationMode::Sunlight => write!(f, "sunlight"),
+            VisualizationMode::Debug=> write!(f, "debug"),
+            VisualizationMode::EquatorDistance=> write!(f, "equator_distance"),
+            VisualizationMode::Fauna => write!(f, "fauna"),
+            VisualizationMode::Hazards => write!(f, "hazards"),
+            VisualizationMode::Relief => write!(f, "relief"),
+        }
+    }
+}
+
+impl FromStr for VisualizationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "biome" => Ok(VisualizationMode::Biome),
+            "altitude" => Ok(VisualizationMode::Altitude),
+            "altitude_with_sea" => Ok(VisualizationMode::AltitudeWithSea),
+            "temperature" => Ok(VisualizationMode::Temperature),
+            "rainfall" => Ok(VisualizationMode::Rainfall),
+            "debug" => Ok(VisualizationMode::Debug),
+            "equator_distance" => Ok(VisualizationMode::EquatorDistance),
+            "fauna" => Ok(VisualizationMode::Fauna),
+            "hazards" => Ok(VisualizationMode::Hazards),
+            "relief" => Ok(VisualizationMode::Relief),
+            other => Err(format!("Unknown visualization mode '{}'.", other))
+        }
+    }
+}
+
+impl World {
+    pub fn generate_image(&self, mode: &VisualizationMode, overlays: &Overlays) -> DynamicImage {
+        self.render(mode, overlays, 1)
+    }
+
+    /// Draws the map at `scale` pixels per tile. Tiles are upscaled as blocks;
+    /// contour lines are traced at the full output resolution.
+    pub fn render(&self, mode: &VisualizationMode, overlays: &Overlays, scale: u32) -> DynamicImage {
+        let mut img = RgbaImage::new(self.width as u32, self.height as u32);
+
+        for tile in &self.tiles {
+            img.put_pixel(tile.x as u32, tile.y as u32, tile.rgb(mode, self, &overlays.palette));
+        }
+
+        self.rivers.iter().for_each(|river| {
+            img.put_pixel(self.tiles[*river].x as u32, self.tiles[*river].y as u32, Rgba([255,0,0,255]));
+        });
+
+        if overlays.ice { self.draw_ice(&mut img); }
+        if overlays.hillshade || matches!(mode, VisualizationMode::Relief) {
+            self.draw_hillshade(&mut img, &overlays.sun);
+        }
+        if overlays.volcanoes { self.draw_volcanoes(&mut img); }
+        if overlays.features { self.draw_features(&mut img); }
+
+        let scale = scale.max(1);
+        if scale > 1 {
+            img = resize(&img, img.width() * scale, img.height() * scale, FilterType::Nearest);
+        }
+        if overlays.contours { self.draw_contours(&mut img, &overlays.isolines, scale as f64); }
+
+        println!("[MapGen] Finished building image.");
+        DynamicImage::ImageRgba8(img)
+    }
+
+    pub fn save_image(
+        &self,
+        mode: &VisualizationMode,
+        overlays: &Overlays,
+        scale: u32,
+        debug: bool
+    ) {
+        let (imagefile, logfile) = {
+            let mut imagefile = PathBuf::from_str("/home/tsrodr/Run/civ-sim/").expect("[MapGen] Could not find project folder.");
+            let mut logfile = imagefile.clone();
+            let file_name = format!("{}-{}", Local::now().format(DATE_FORMAT), mode);
+
+            imagefile.push("images");
+            imagefile.push(&file_name);
+            imagefile.set_extension("png");
+
+            logfile.push("logs");
+            logfile.push(&file_name);
+            logfile.set_extension("log");
+
+            (imagefile, logfile)
+        };
+        
+        if debug {
+            let mut log = String::from("id,altitude,temperature,rainfall\n");
+            for tile in &self.tiles {
+                log.push_str(&format!("{},{},{},{}\n", tile.id, tile.altitude, tile.temperature, tile.rainfall));
+            }
+            println!("[MapGen] Writing log to file {}", &logfile.display());
+            std::fs::write(&logfile, log).unwrap();
+            std::fs::write(
+                logfile.with_extension("json"),
+                serde_json::to_string_pretty(&self.metadata).unwrap()
+            ).unwrap();
+        }
+
+        if self.year > 0 {
+            let history = serde_json::json!({
+                "year": self.year,
+                "events": self.events,
+                "climate": self.climate,
+            });
+            let historyfile = imagefile.with_extension("history.json");
+            println!("[MapGen] Writing history to file {}", &historyfile.display());
+            if let Err(e) = std::fs::write(&historyfile, serde_json::to_string_pretty(&history).unwrap()) {
+                eprintln!("[MapGen] Could not save history to file: {:?}", e);
+            }
+        }
+        
+        println!("[MapGen] Writing image to file {}", &imagefile.display());
+        let mut img = self.render(mode, overlays, scale).to_rgba8();
+        if overlays.annotations { self.annotate(&mut img, mode, overlays, scale); }
+        match save_buffer(
+            imagefile,
+            &img,
+            img.width(),
+            img.height(),
+            Rgba8
+        ) {
+            Ok(()) => { println!("[MapGen] Map saved!") },
+            e => { eprintln!("[MapGen] Could not save image to file: {:?}", e) }
+        };
+    }
+}
+
+impl Biome {
+    pub fn color(&self) -> [u8; 3] {
+        match self {
+            Biome::Frozen =>     [255, 255, 255],
+            Biome::Tundra =>     [150, 140, 130],
+            Biome::Boreal=>      [150, 150, 130],
+            Biome::Temperate =>  [ 35,  80,  35],
+            Biome::Rainforest => [ 90, 120,  35],
+            Biome::Wetland =>    [ 55,  80,  75],
+            Biome::Plains =>     [180, 180, 135],
+            Biome::Desert =>     [255, 235, 185],
+            Biome::Hill =>       [105, 110, 100],
+            Biome::Mountain =>   [125, 130, 130],
+            Biome::Peak =>       [215, 215, 215],
+            Biome::Coast =>      [ 30,  75, 220],
+            Biome::Sea =>        [ 25,  25, 200],
+            Biome::Debug =>      [255,   0,   0]
+        }
+    }
+}
+
+impl Species {
+    pub fn color(&self) -> [u8; 3] {
+        match self {
+            Species::Grazers =>       [230, 200,  40],
+            Species::ForestGame =>    [ 40, 200,  60],
+            Species::FishRuns =>      [ 40, 220, 230],
+            Species::MarineMammals => [ 90,  70, 230],
+        }
+    }
+}
+
+impl Hazard {
+    pub fn color(&self) -> [u8; 3] {
+        match self {
+            Hazard::Flood =>      [ 40, 110, 255],
+            Hazard::Drought =>    [230, 150,  30],
+            Hazard::Eruption =>   [255,  30,  20],
+            Hazard::Earthquake => [170,  60, 200],
+        }
+    }
+}
+
+/// Linear interpolation between two colors; `t` of 0.0 gives `a`.
+fn mix(a: [u8; 3], b: [u8; 3], t: f64) -> [u8; 3] {
+    let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    [channel(a[0], b[0]), channel(a[1], b[1]), channel(a[2], b[2])]
+}
+
+/// Altitude in gray, with the sea in blue.
+fn with_sea(altitude: f64, sea_level: f64) -> [u8; 3] {
+    let color = scale_f64_to_u8(altitude);
+    if altitude <= sea_level { [0, 0, color] }
+    else { [color/2, color/2, 0] }
+}
+
+/// Hypsometric tint: deepening blues below sea level, green through tan and
+/// brown to snow above it.
+fn hypsometric(altitude: f64, sea_level: f64) -> [u8; 3] {
+    if altitude <= sea_level {
+        let depth = ((sea_level - altitude) / (1.0 + sea_level)).clamp(0.0, 1.0);
+        mix([110, 160, 220], [20, 50, 130], depth)
+    } else {
+        let height = ((altitude - sea_level) / (1.0 - sea_level)).clamp(0.0, 1.0);
+        let stops = [[80, 140, 70], [200, 190, 120], [140, 100, 70], [250, 250, 250]];
+        let position = height * (stops.len() - 1) as f64;
+        let i = (position as usize).min(stops.len() - 2);
+        mix(stops[i], stops[i + 1], position - i as f64)
+    }
+}
+
+impl Tile {
+    /// Biome color blended with the ecotone's.
+    pub fn color(&self, palette: &Palette) -> [u8; 3] {
+        mix(palette.biome(self.biome), palette.biome(self.ecotone), self.blend)
+    }
+
+    pub fn rgb(&self, mode: &VisualizationMode, world: &World, palette: &Palette) -> Rgba<u8> {
+        let rgb: [u8; 4] = match mode {
+            VisualizationMode::Debug => {
+                let color = [
+                    scale_f64_to_u8(self.altitude),
+                    scale_f64_to_u8(self.rainfall),
+                    scale_f64_to_u8(self.temperature),
+                    255
+                ];
+                [color[0], color[1], color[2], color[3]]
+            },
+            VisualizationMode::Biome => {
+                let alpha: u8 = scale_f64_to_u8(self.altitude);
+                let [r, g, b] = self.color(palette);
+                [r, g, b, alpha]
+            },
+            VisualizationMode::Altitude => {
+                let [r, g, b] = palette.altitude(self.altitude, world.params.sea_level);
+                [r, g, b, 255]
+            },
+            VisualizationMode::AltitudeWithSea => {
+                let [r, g, b] = with_sea(self.altitude, world.params.sea_level);
+                [r, g, b, 255]
+            },
+            VisualizationMode::Rainfall => {
+                let [r, g, b] = palette.field(Field::Rainfall, self.rainfall);
+                [r, g, b, 255]
+            },
+            VisualizationMode::Temperature => {
+                let [r, g, b] = palette.field(Field::Temperature, self.temperature);
+                [r, g, b, 255]
+            },
+            VisualizationMode::Fauna => {
+                let mut color = [0.0; 3];
+                for species in Species::ALL {
+                    let density = self.fauna.density(species);
+                    color.iter_mut().zip(species.color()).for_each(|(c, s)| *c += s as f64 * density);
+                }
+                let [r, g, b] = color.map(|c| c.min(255.0) as u8);
+                [r, g, b, 255]
+            },
+            VisualizationMode::Hazards => {
+                let mut color = [0.0; 3];
+                for hazard in Hazard::ALL {
+                    let risk = self.risk(hazard);
+                    color.iter_mut().zip(hazard.color()).for_each(|(c, h)| *c += h as f64 * risk);
+                }
+                // Recently struck tiles fade towards white
+                let [r, g, b] = color.map(|c| (c.min(255.0) + (255.0 - c.min(255.0)) * self.damage) as u8);
+                [r, g, b, 255]
+            },
+            VisualizationMode::Relief => {
+                let [r, g, b] = hypsometric(self.altitude, world.params.sea_level);
+                [r, g, b, 255]
+            },
+            VisualizationMode::EquatorDistance => {
+                let color = scale_f64_to_u8(-f64::abs(self.latitude));
+                [color, color, color, 255]
+            },
+            _ => unreachable!()
+        };
+
+        Rgba([rgb[0],rgb[1],rgb[2],rgb[3]])
+    }
+}
+
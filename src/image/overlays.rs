@@ -0,0 +1,99 @@
+use nannou::image::{Rgba, RgbaImage};
+use crate::{
+    image::{contours::Isolines, palette::Palette, relief::Sun},
+    map::{
+        features::FeatureKind,
+        ice::IceKind,
+        volcanism::Volcano,
+        world::World
+    },
+    utils::helpers::neighbors8
+};
+
+/// Layers drawn on top of any `VisualizationMode`, and the colors it uses.
+#[derive(Default, Clone)]
+pub struct Overlays {
+    pub features: bool,
+    pub ice: bool,
+    pub volcanoes: bool,
+    /// Multiply hillshade over the mode's colors
+    pub hillshade: bool,
+    pub sun: Sun,
+    pub contours: bool,
+    pub isolines: Vec<Isolines>,
+    pub palette: Palette,
+    /// Legend, seed, scale bar and compass on saved images
+    pub annotations: bool,
+}
+
+pub fn blend(pixel: &mut Rgba<u8>, color: [u8; 3], alpha: f64) {
+    let alpha = alpha.clamp(0.0, 1.0);
+    for c in 0..3 {
+        pixel[c] = (pixel[c] as f64 * (1.0 - alpha) + color[c] as f64 * alpha) as u8;
+    }
+    pixel[3] = pixel[3].max((alpha * 255.0) as u8);
+}
+
+impl FeatureKind {
+    pub fn color(&self) -> [u8; 3] {
+        match self {
+            FeatureKind::Pass =>    [255, 140,   0],
+            FeatureKind::Isthmus => [255, 220,   0],
+            FeatureKind::Strait =>  [  0, 230, 230],
+            FeatureKind::Harbor =>  [230,   0, 200],
+        }
+    }
+}
+
+impl IceKind {
+    pub fn color(&self) -> [u8; 3] {
+        match self {
+            IceKind::Sheet =>   [235, 245, 255],
+            IceKind::Snowcap => [255, 255, 255],
+            IceKind::Glacier => [190, 225, 250],
+            IceKind::SeaIce =>  [210, 225, 240],
+        }
+    }
+}
+
+impl Volcano {
+    pub fn color(&self) -> [u8; 3] {
+        match self {
+            Volcano::Active =>  [255,  40,   0],
+            Volcano::Dormant => [120,  40,  30],
+        }
+    }
+}
+
+impl World {
+    /// Marks each vent, with a ring of its color around it.
+    pub fn draw_volcanoes(&self, img: &mut RgbaImage) {
+        for tile in self.tiles.iter().filter(|tile| tile.volcano.is_some()) {
+            let color = tile.volcano.unwrap().color();
+            for i in neighbors8(tile.id, self.width, self.size) {
+                let neighbor = &self.tiles[i];
+                blend(img.get_pixel_mut(neighbor.x as u32, neighbor.y as u32), color, 0.6);
+            }
+            blend(img.get_pixel_mut(tile.x as u32, tile.y as u32), color, 1.0);
+        }
+    }
+
+    pub fn draw_ice(&self, img: &mut RgbaImage) {
+        for tile in &self.tiles {
+            if let Some(ice) = tile.ice {
+                let pixel = img.get_pixel_mut(tile.x as u32, tile.y as u32);
+                blend(pixel, ice.kind.color(), 0.5 + 0.5 * ice.thickness);
+            }
+        }
+    }
+
+    pub fn draw_features(&self, img: &mut RgbaImage) {
+        for feature in &self.features {
+            for i in &feature.tiles {
+                let tile = &self.tiles[*i];
+                let pixel = img.get_pixel_mut(tile.x as u32, tile.y as u32);
+                blend(pixel, feature.kind.color(), 0.4 + 0.6 * feature.score);
+            }
+        }
+    }
+}
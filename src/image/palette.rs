@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+use serde::Deserialize;
+use crate::map::tile::{Biome, Field};
+
+/// Palettes bundled with the binary, selectable by name.
+pub const PALETTES: [(&str, &str); 3] = [
+    ("classic", include_str!("../palettes/classic.json")),
+    ("perceptual", include_str!("../palettes/perceptual.json")),
+    ("atlas", include_str!("../palettes/atlas.json")),
+];
+
+/// Built-in colormaps, as evenly spaced stops.
+const COLORMAPS: [(&str, &[[u8; 3]]); 6] = [
+    ("viridis", &[
+        [ 68,   1,  84], [ 72,  40, 120], [ 62,  74, 137], [ 49, 104, 142], [ 38, 130, 142],
+        [ 31, 158, 137], [ 53, 183, 121], [109, 205,  89], [180, 222,  44], [253, 231,  37],
+    ]),
+    ("terrain", &[
+        [ 51,  51, 153], [  0, 153, 255], [  0, 204, 102], [153, 230, 128],
+        [255, 255, 153], [204, 178, 127], [128,  92,  84], [255, 255, 255],
+    ]),
+    ("temperature", &[
+        [ 5,  48,  97], [ 33, 102, 172], [ 67, 147, 195], [146, 197, 222], [209, 229, 240],
+        [247, 247, 247], [253, 219, 199], [244, 165, 130], [214,  96,  77], [178,  24,  43], [103,   0,  31],
+    ]),
+    ("gray", &[[0, 0, 0], [255, 255, 255]]),
+    ("reds", &[[0, 0, 0], [255, 0, 0]]),
+    ("blues", &[[0, 0, 0], [0, 0, 255]]),
+];
+
+/// A gradient as written in a palette file: a colormap name, or a list of
+/// `[position, [r, g, b]]` stops with positions from 0.0 to 1.0.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GradientSpec {
+    Named(String),
+    Stops(Vec<(f64, [u8; 3])>),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "GradientSpec")]
+pub struct Gradient {
+    stops: Vec<(f64, [u8; 3])>,
+}
+
+impl TryFrom<GradientSpec> for Gradient {
+    type Error = String;
+
+    fn try_from(spec: GradientSpec) -> Result<Gradient, String> {
+        match spec {
+            GradientSpec::Named(name) => Gradient::named(&name),
+            GradientSpec::Stops(mut stops) => {
+                if stops.is_empty() { return Err("a gradient needs at least one stop".to_string()); }
+                stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+                Ok(Gradient { stops })
+            }
+        }
+    }
+}
+
+impl Gradient {
+    pub fn named(name: &str) -> Result<Gradient, String> {
+        let (_, colors) = COLORMAPS.iter()
+            .find(|(colormap, _)| *colormap == name)
+            .ok_or(format!(
+                "Unknown colormap '{}'; expected one of {}.",
+                name,
+                COLORMAPS.iter().map(|(name, _)| *name).collect::<Vec<&str>>().join(", ")
+            ))?;
+        let last = (colors.len() - 1).max(1) as f64;
+        Ok(Gradient { stops: colors.iter().enumerate().map(|(i, color)| (i as f64 / last, *color)).collect() })
+    }
+
+    /// Color at `t`, from 0.0 to 1.0, interpolated between the nearest stops.
+    pub fn sample(&self, t: f64) -> [u8; 3] {
+        let t = t.clamp(0.0, 1.0);
+        let upper = self.stops.iter().position(|(position, _)| *position >= t).unwrap_or(self.stops.len() - 1);
+        if upper == 0 { return self.stops[0].1; }
+        let ((p0, c0), (p1, c1)) = (self.stops[upper - 1], self.stops[upper]);
+        let f = if p1 > p0 { (t - p0) / (p1 - p0) } else { 1.0 };
+        [0, 1, 2].map(|c| (c0[c] as f64 + (c1[c] as f64 - c0[c] as f64) * f).round() as u8)
+    }
+}
+
+fn default_altitude() -> Gradient { Gradient::named("gray").unwrap() }
+fn default_temperature() -> Gradient { Gradient::named("reds").unwrap() }
+fn default_rainfall() -> Gradient { Gradient::named("blues").unwrap() }
+
+/// Colors for biomes and scalar fields. Biomes left out keep their built-in
+/// color, so a palette file may list only the ones it changes. The entries of
+/// the JS map in `pallette.html` become `"name": [r, g, b]` keys of `biomes`;
+/// its `hills` and `peaks` names are accepted too.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Palette {
+    #[serde(default)]
+    pub biomes: BTreeMap<Biome, [u8; 3]>,
+    #[serde(default = "default_altitude")]
+    pub altitude: Gradient,
+    /// Position in the altitude gradient that sea level is drawn at; without
+    /// it the gradient spans -1.0 to 1.0 regardless of sea level
+    #[serde(default)]
+    pub sea_stop: Option<f64>,
+    #[serde(default = "default_temperature")]
+    pub temperature: Gradient,
+    #[serde(default = "default_rainfall")]
+    pub rainfall: Gradient,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::load("classic").unwrap()
+    }
+}
+
+impl Palette {
+    /// Loads a bundled palette by name, or a JSON palette file by path.
+    pub fn load(source: &str) -> Result<Palette, String> {
+        let data = match PALETTES.iter().find(|(name, _)| *name == source) {
+            Some((_, data)) => data.to_string(),
+            None => std::fs::read_to_string(source).map_err(|e| format!(
+                "'{}' is neither a bundled palette ({}) nor a readable file: {}",
+                source,
+                PALETTES.iter().map(|(name, _)| *name).collect::<Vec<&str>>().join(", "),
+                e
+            ))?,
+        };
+        serde_json::from_str(&data).map_err(|e| format!("Palette '{}' is not valid: {}", source, e))
+    }
+
+    pub fn biome(&self, biome: Biome) -> [u8; 3] {
+        self.biomes.get(&biome).copied().unwrap_or(biome.color())
+    }
+
+    /// Color of an altitude. With `sea_stop` set, the gradient is stretched
+    /// on either side so that `sea_level` falls exactly on that stop.
+    pub fn altitude(&self, value: f64, sea_level: f64) -> [u8; 3] {
+        let stop = match self.sea_stop {
+            Some(stop) => stop,
+            None => return self.field(Field::Altitude, value),
+        };
+        let t = if value <= sea_level {
+            stop * (value + 1.0) / (sea_level + 1.0).max(f64::EPSILON)
+        } else {
+            stop + (1.0 - stop) * (value - sea_level) / (1.0 - sea_level).max(f64::EPSILON)
+        };
+        self.altitude.sample(t)
+    }
+
+    /// Color of a field value within [-1.0, 1.0].
+    pub fn field(&self, field: Field, value: f64) -> [u8; 3] {
+        let gradient = match field {
+            Field::Altitude => &self.altitude,
+            Field::Temperature => &self.temperature,
+            Field::Rainfall => &self.rainfall,
+        };
+        gradient.sample((value + 1.0) / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atlas_breaks_at_sea_level() {
+        let atlas = Palette::load("atlas").unwrap();
+        for sea_level in [-0.2, 0.0, 0.3] {
+            assert_eq!(atlas.altitude(sea_level, sea_level), [120, 170, 210], "sea level {}", sea_level);
+            assert_eq!(atlas.altitude(sea_level + 1e-9, sea_level), [90, 140, 80], "sea level {}", sea_level);
+            assert_eq!(atlas.altitude(-1.0, sea_level), [20, 40, 90]);
+            assert_eq!(atlas.altitude(1.0, sea_level), [250, 250, 250]);
+        }
+    }
+
+    #[test]
+    fn palettes_without_a_sea_stop_ignore_sea_level() {
+        let classic = Palette::load("classic").unwrap();
+        assert_eq!(classic.altitude(0.3, 0.5), classic.field(Field::Altitude, 0.3));
+    }
+}
@@ -0,0 +1,77 @@
+use nannou::image::RgbaImage;
+use crate::{
+    map::world::World,
+    utils::cli::Args
+};
+
+/// Tile widths per unit of altitude at an exaggeration of 1.0, so that a
+/// full-resolution world reads as terrain rather than as a flat plate.
+const RELIEF_SCALE: f64 = 60.0;
+/// Brightest a sunlit slope gets, relative to flat ground.
+const MAX_HIGHLIGHT: f64 = 1.3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sun {
+    /// Degrees clockwise from north
+    pub azimuth: f64,
+    /// Degrees above the horizon
+    pub elevation: f64,
+    pub exaggeration: f64,
+}
+
+impl Default for Sun {
+    fn default() -> Sun {
+        Sun { azimuth: 315.0, elevation: 45.0, exaggeration: 1.0 }
+    }
+}
+
+impl Sun {
+    pub fn from_args(args: &Args) -> Sun {
+        Sun {
+            azimuth: args.sun_azimuth,
+            elevation: args.sun_elevation,
+            exaggeration: args.exaggeration,
+        }
+    }
+
+    /// Unit vector pointing at the sun; x is east, y is north, z is up.
+    fn direction(&self) -> [f64; 3] {
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        [azimuth.sin() * elevation.cos(), azimuth.cos() * elevation.cos(), elevation.sin()]
+    }
+}
+
+impl World {
+    /// Brightness of every tile lit by `sun`, relative to flat ground, which
+    /// gets 1.0. The sea surface counts as flat.
+    pub fn hillshade(&self, sun: &Sun) -> Vec<f64> {
+        let light = sun.direction();
+        let flat = light[2].max(f64::EPSILON);
+        // Zoomed worlds have tiles closer together, so slopes get steeper per tile
+        let z = RELIEF_SCALE * sun.exaggeration * self.viewport.scale;
+        let height = |x: i64, y: i64| {
+            let (x, y) = (x.clamp(0, self.width as i64 - 1), y.clamp(0, self.height as i64 - 1));
+            self.tiles[x as usize + y as usize * self.width].altitude.max(self.params.sea_level)
+        };
+
+        self.tiles.iter().map(|tile| {
+            let (x, y) = (tile.x as i64, tile.y as i64);
+            let east = (height(x + 1, y) - height(x - 1, y)) / 2.0 * z;
+            // Rows grow southwards
+            let north = (height(x, y - 1) - height(x, y + 1)) / 2.0 * z;
+            let length = (east * east + north * north + 1.0).sqrt();
+            let light = (-east * light[0] - north * light[1] + light[2]) / length;
+            (light.max(0.0) / flat).min(MAX_HIGHLIGHT)
+        }).collect()
+    }
+
+    /// Multiplies the color of every pixel by its tile's hillshade.
+    pub fn draw_hillshade(&self, img: &mut RgbaImage, sun: &Sun) {
+        for (tile, shade) in self.tiles.iter().zip(self.hillshade(sun)) {
+            let pixel = img.get_pixel_mut(tile.x as u32, tile.y as u32);
+            for c in 0..3 {
+                pixel[c] = (pixel[c] as f64 * shade).min(255.0) as u8;
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use lazy_static::lazy_static;
+use nannou::{
+    image::{Rgba, RgbaImage},
+    text::{font::default_notosans, rt::point, Font, Scale}
+};
+
+lazy_static! {
+    static ref FONT: Font = default_notosans();
+}
+
+/// Width in pixels of `text` rendered at `size`.
+pub fn text_width(text: &str, size: f32) -> f32 {
+    FONT.layout(text, Scale::uniform(size), point(0.0, 0.0))
+        .filter_map(|glyph| glyph.pixel_bounding_box())
+        .map(|bounds| bounds.max.x as f32)
+        .fold(0.0, f32::max)
+}
+
+/// Draws a single line of text with its top-left corner at (x, y), alpha-blended over the image.
+pub fn draw_text(img: &mut RgbaImage, text: &str, x: i32, y: i32, size: f32, color: Rgba<u8>) {
+    let scale = Scale::uniform(size);
+    let ascent = FONT.v_metrics(scale).ascent;
+
+    for glyph in FONT.layout(text, scale, point(x as f32, y as f32 + ascent)) {
+        if let Some(bounds) = glyph.pixel_bounding_box() {
+            glyph.draw(|gx, gy, coverage| {
+                let (px, py) = (bounds.min.x + gx as i32, bounds.min.y + gy as i32);
+                if px < 0 || py < 0 || px >= img.width() as i32 || py >= img.height() as i32 { return; }
+                let pixel = img.get_pixel_mut(px as u32, py as u32);
+                let alpha = coverage * color[3] as f32 / 255.0;
+                for c in 0..3 {
+                    pixel[c] = (pixel[c] as f32 * (1.0 - alpha) + color[c] as f32 * alpha) as u8;
+                }
+                pixel[3] = pixel[3].max((alpha * 255.0) as u8);
+            });
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use clap::Parser;
 use lazy_static::lazy_static;
 use nannou::{
@@ -5,26 +6,56 @@ use nannou::{
     wgpu::Texture
 };
 use crate::{
-    image::VisualizationMode, 
-    map::world::{WorldBuilder, World, WorldParameters},
-    utils::{cli::Args, helpers::load_parameters}
+    image::{
+        VisualizationMode,
+        contours::Isolines,
+        overlays::Overlays,
+        palette::{Palette, PALETTES},
+        relief::Sun
+    },
+    map::{
+        guide::AltitudeGuides,
+        world::{WorldBuilder, World, WorldParameters},
+        presets::{PRESETS, load_preset}
+    },
+    tools::{sweep::run_sweep, stats::run_stats, seed_search::run_seed_search, overview::run_overview},
+    utils::{
+        cli::{Args, Command},
+        helpers::{load_parameters, load_from_args, parameters_path},
+        seed::WorldSeed,
+        watch::FileWatcher
+    }
 };
 
 pub mod utils;
 pub mod map;
 pub mod image;
 pub mod noise_sampler;
+pub mod tools;
+
+const ZOOM_FACTOR: f64 = 4.0;
+const YEARS_PER_STEP: u32 = 10;
 
 lazy_static! {
     static ref ARGS: Args = Args::parse();
+    static ref PARAMETERS_PATH: String = parameters_path(&ARGS);
 }
 
 struct Model {
     _window: window::Id,
     world: World,
+    zoom_stack: Vec<World>,
     texture: Texture,
     parameters: WorldParameters,
+    /// Heightmap and land guide loaded for `parameters`
+    guides: Arc<AltitudeGuides>,
+    preset: Option<usize>,
+    /// Index into `PALETTES` of the bundled palette in use, if any
+    palette: Option<usize>,
     visual_mode: VisualizationMode,
+    overlays: Overlays,
+    watcher: Option<FileWatcher>,
+    error: Option<String>,
 }
 
 fn main() {
@@ -34,20 +65,132 @@ fn main() {
         ARGS.x,
         ARGS.y
     );
-    nannou::app(model).loop_mode(LoopMode::Wait).run();
+
+    if let Some(command) = &ARGS.command {
+        let result = match command {
+            Command::Sweep { grid, output } => run_sweep(&ARGS, grid, output),
+            Command::Stats { output } => run_stats(&ARGS, output),
+            Command::SeedSearch { criteria, count, top, output } => {
+                run_seed_search(&ARGS, criteria, *count, *top, output.as_ref())
+            },
+            Command::Overview { output, downsample, cache } => run_overview(&ARGS, output, *downsample, *cache),
+        };
+        if let Err(report) = result {
+            eprintln!("{}", report);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    nannou::app(model).update(update).loop_mode(LoopMode::Wait).run();
 }
 
 fn model(app: &App) -> Model {
-    let parameters = load_parameters();
+    let preset = ARGS.preset.as_ref()
+        .and_then(|name| PRESETS.iter().position(|(preset, _)| preset == name));
+    let parameters = load_from_args(&ARGS).unwrap_or_else(|report| {
+        eprintln!("{}", report);
+        std::process::exit(1);
+    });
     let _window = app.new_window()
         .key_pressed(handle_keys)
         .view(view)
         .build()
         .unwrap();
     let visual_mode = VisualizationMode::Biome;
-    let world = WorldBuilder::new(&ARGS, &parameters).build();
-    let texture = Texture::from_image(app, &world.generate_image(&visual_mode));
-    Model { _window, world, texture, parameters, visual_mode }
+    let guides = AltitudeGuides::load(&ARGS, &parameters).map(Arc::new).unwrap_or_else(|report| {
+        eprintln!("{}", report);
+        std::process::exit(1);
+    });
+    let world = WorldBuilder::new(&ARGS, &parameters, &guides).unwrap_or_else(|report| {
+        eprintln!("{}", report);
+        std::process::exit(1);
+    }).build();
+    let isolines = Isolines::from_args(&ARGS).unwrap_or_else(|report| {
+        eprintln!("{}", report);
+        std::process::exit(1);
+    });
+    let palette = Palette::load(&ARGS.palette).unwrap_or_else(|report| {
+        eprintln!("{}", report);
+        std::process::exit(1);
+    });
+    let overlays = Overlays {
+        sun: Sun::from_args(&ARGS),
+        contours: !ARGS.contour.is_empty(),
+        isolines,
+        palette,
+        annotations: ARGS.annotate,
+        ..Overlays::default()
+    };
+    let texture = Texture::from_image(app, &world.generate_image(&visual_mode, &overlays));
+    let watcher = ARGS.preset.is_none().then(|| {
+        let proxy = app.create_proxy();
+        FileWatcher::spawn(PARAMETERS_PATH.as_str().into(), move || { proxy.wakeup().ok(); })
+    });
+    Model {
+        _window,
+        world,
+        zoom_stack: Vec::new(),
+        texture,
+        parameters,
+        guides,
+        preset,
+        palette: PALETTES.iter().position(|(name, _)| *name == ARGS.palette),
+        visual_mode,
+        overlays,
+        watcher,
+        error: None
+    }
+}
+
+fn redraw(app: &App, model: &mut Model) {
+    model.texture = Texture::from_image(app, &model.world.generate_image(&model.visual_mode, &model.overlays));
+}
+
+/// Builds a new world from the current parameters, keeping `seed` if given,
+/// or shows why it could not be built and keeps the current one.
+/// Builds a world from the given parameters, keeping the current world and parameters if that fails.
+fn rebuild(app: &App, model: &mut Model, parameters: WorldParameters, seed: Option<&WorldSeed>) -> bool {
+    let built = model.guides.refit(&ARGS, &parameters).map(Arc::new).and_then(|guides| {
+        let mut builder = WorldBuilder::new(&ARGS, &parameters, &guides)?;
+        if let Some(seed) = seed { builder.with_seed(seed); }
+        Ok((builder.build(), guides))
+    });
+    match built {
+        Ok((world, guides)) => {
+            model.world = world;
+            model.guides = guides;
+            model.parameters = parameters;
+            model.zoom_stack.clear();
+            model.error = None;
+            redraw(app, model);
+            true
+        },
+        Err(report) => {
+            eprintln!("{}", report);
+            model.error = Some(report);
+            false
+        }
+    }
+}
+
+fn update(app: &App, model: &mut Model, _update: Update) {
+    // Reload the parameters file when it changes, keeping seeds and visualization mode
+    if model.watcher.as_ref().is_some_and(|watcher| watcher.changed()) {
+        println!("[MapGen] Parameters file changed; reloading.");
+        match load_parameters(&PARAMETERS_PATH) {
+            Ok(parameters) => {
+                let seed = model.world.seed.clone();
+                if rebuild(app, model, parameters, Some(&seed)) {
+                    model.preset = None;
+                }
+            },
+            Err(report) => {
+                eprintln!("{}", report);
+                model.error = Some(report);
+            }
+        }
+    }
 }
 
 fn view(app: &App, model: &Model, frame: Frame) {
@@ -55,6 +198,16 @@ fn view(app: &App, model: &Model, frame: Frame) {
     let draw = app.draw();
     let rect = app.window_rect();
     draw.texture(&model.texture).w_h(rect.w(), rect.h());
+    if let Some(error) = &model.error {
+        let area = rect.pad(20.0);
+        draw.text(error)
+            .xy(area.xy())
+            .wh(area.wh())
+            .left_justify()
+            .align_text_top()
+            .font_size(14)
+            .color(RED);
+    }
     draw.to_frame(app, &frame).unwrap();
 }
 
@@ -66,21 +219,112 @@ fn handle_keys(app: &App, model: &mut Model, key: Key) {
             VisualizationMode::Altitude => model.visual_mode = VisualizationMode::AltitudeWithSea,
             VisualizationMode::AltitudeWithSea => model.visual_mode = VisualizationMode::Rainfall,
             VisualizationMode::Rainfall => model.visual_mode = VisualizationMode::Temperature,
-            VisualizationMode::Temperature => model.visual_mode = VisualizationMode::Biome,
+            VisualizationMode::Temperature => model.visual_mode = VisualizationMode::Fauna,
+            VisualizationMode::Fauna => model.visual_mode = VisualizationMode::Hazards,
+            VisualizationMode::Hazards => model.visual_mode = VisualizationMode::Relief,
+            VisualizationMode::Relief => model.visual_mode = VisualizationMode::Biome,
             _ => unreachable!()
         };
         println!("[MapGen] Mode switched to {}.", model.visual_mode);
-        model.texture = Texture::from_image(app, &model.world.generate_image(&model.visual_mode));
+        redraw(app, model);
     };
 
     // S: save current map
     if matches!(key, Key::S) {
-        model.world.save_image(&model.visual_mode, ARGS.debug);
+        model.world.save_image(&model.visual_mode, &model.overlays, ARGS.export_scale, ARGS.debug);
     }
 
     // N: generate new map
     if matches!(key, Key::N) {
-        model.world = WorldBuilder::new(&ARGS, &model.parameters).build();
-        model.texture = Texture::from_image(app, &model.world.generate_image(&model.visual_mode));
+        rebuild(app, model, model.parameters.clone(), None);
+    }
+
+    // P: switch to the next preset and generate a new map with it
+    if matches!(key, Key::P) {
+        let next = model.preset.map_or(0, |i| (i + 1) % PRESETS.len());
+        match load_preset(PRESETS[next].0) {
+            Ok(parameters) => {
+                if rebuild(app, model, parameters, None) {
+                    println!("[MapGen] Switched to preset {}.", PRESETS[next].0);
+                    model.preset = Some(next);
+                }
+            },
+            Err(report) => eprintln!("{}", report)
+        }
+    }
+
+    // Z: regenerate the area under the cursor at a higher resolution
+    if matches!(key, Key::Z) {
+        let rect = app.window_rect();
+        let u = ((app.mouse.x - rect.left()) / rect.w()) as f64;
+        let v = ((rect.top() - app.mouse.y) / rect.h()) as f64;
+        let zoomed = model.world.zoom(&ARGS, u, v, ZOOM_FACTOR);
+        model.zoom_stack.push(std::mem::replace(&mut model.world, zoomed));
+        redraw(app, model);
+    }
+
+    // F: toggle the strategic features overlay
+    if matches!(key, Key::F) {
+        model.overlays.features = !model.overlays.features;
+        redraw(app, model);
+    }
+
+    // I: toggle the ice overlay
+    if matches!(key, Key::I) {
+        model.overlays.ice = !model.overlays.ice;
+        redraw(app, model);
+    }
+
+    // V: toggle the volcanoes overlay
+    if matches!(key, Key::V) {
+        model.overlays.volcanoes = !model.overlays.volcanoes;
+        redraw(app, model);
+    }
+
+    // T: run the world forward in time, firing hazards
+    if matches!(key, Key::T) {
+        let events = model.world.advance(YEARS_PER_STEP);
+        println!("[MapGen] Now in year {}; {} hazards fired.", model.world.year, events.len());
+        redraw(app, model);
+    }
+
+    // H: toggle hillshading over the current mode
+    if matches!(key, Key::H) {
+        model.overlays.hillshade = !model.overlays.hillshade;
+        redraw(app, model);
+    }
+
+    // C: toggle contour lines
+    if matches!(key, Key::C) {
+        model.overlays.contours = !model.overlays.contours;
+        redraw(app, model);
+    }
+
+    // K: switch to the next bundled palette
+    if matches!(key, Key::K) {
+        let next = model.palette.map_or(0, |i| (i + 1) % PALETTES.len());
+        match Palette::load(PALETTES[next].0) {
+            Ok(palette) => {
+                println!("[MapGen] Switched to palette {}.", PALETTES[next].0);
+                model.palette = Some(next);
+                model.overlays.palette = palette;
+                redraw(app, model);
+            },
+            Err(report) => eprintln!("{}", report)
+        }
+    }
+
+    // A: toggle annotations on saved maps
+    if matches!(key, Key::A) {
+        model.overlays.annotations = !model.overlays.annotations;
+        println!("[MapGen] Annotations {}.", if model.overlays.annotations { "on" } else { "off" });
+    }
+
+    // X: go back to the previous zoom level
+    if matches!(key, Key::X) {
+        if let Some(world) = model.zoom_stack.pop() {
+            model.world = world;
+            redraw(app, model);
+        }
     }
 }
@@ -0,0 +1,226 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc
+};
+use noise::{Fbm, SuperSimplex};
+use rayon::prelude::*;
+use crate::{
+    map::{
+        cleanup::cleanup,
+        fauna::populate,
+        guide::AltitudeGuides,
+        hazards::assess,
+        ice::generate_ice,
+        normalize::NormalizationRecord,
+        tile::Tile,
+        volcanism::raise_volcanoes,
+        world::{mark_coast, Viewport, WorldBuilder, WorldParameters}
+    },
+    utils::{cli::Args, seed::WorldSeed}
+};
+
+pub const CHUNK_SIZE: usize = 128;
+
+/// Longest side, in tiles, of the coarse sample normalizations and thresholds are fitted to.
+const CALIBRATION_SIDE: f64 = 512.0;
+
+pub struct Chunk {
+    pub cx: i64,
+    pub cy: i64,
+    /// `CHUNK_SIZE * CHUNK_SIZE` tiles in row order; ids are local to the
+    /// chunk while x and y hold world coordinates.
+    pub tiles: Vec<Tile>,
+}
+
+/// A world of unbounded size, generated one fixed-size chunk at a time and
+/// kept in a least-recently-used cache.
+///
+/// Normalizations and thresholds need the whole world, so they are fitted
+/// once to a coarse sample of the `args.x` by `args.y` extent and every chunk
+/// applies them as they are. The extent also places the equator, the land
+/// guides and the volcanoes; chunks past it still generate, and only get
+/// colder beyond the poles.
+///
+/// Chunk boundaries: each chunk is generated with a halo wide enough for every
+/// neighborhood pass, which is discarded afterwards, so a chunk's tiles match
+/// what a single large world would produce:
+/// - coastline smoothing reads one tile further per pass, and small islands and
+///   lakes must be seen whole, so the halo also holds the largest one removed;
+/// - coast and shore detection read one tile;
+/// - glaciers flow in from up to `flow_passes` tiles away;
+/// - eruption risk reaches `eruption_radius`.
+///
+/// Strategic features are only detected on whole `World`s. River generation
+/// is not part of the build pipeline yet; when it is, rivers have to stop at
+/// chunk edges, as a chunk cannot see past its halo.
+pub struct ChunkedWorld {
+    params: WorldParameters,
+    seed: WorldSeed,
+    noise: [Fbm<SuperSimplex>; 4],
+    guides: Arc<AltitudeGuides>,
+    normalization: Vec<NormalizationRecord>,
+    /// World size the equator, guides and volcanoes are laid out in
+    extent: (f64, f64),
+    halo: usize,
+    capacity: usize,
+    chunks: HashMap<(i64, i64), Chunk>,
+    recent: VecDeque<(i64, i64)>,
+}
+
+/// Tiles around a chunk that its neighborhood passes read from.
+fn halo(params: &WorldParameters) -> usize {
+    let cleanup = params.cleanup.as_ref().map_or(0, |cleanup| {
+        cleanup.smoothing_passes + cleanup.min_island_area.max(cleanup.min_lake_area) + 1
+    });
+    let flow = params.ice.as_ref().map_or(0, |ice| ice.flow_passes);
+    let eruption = params.hazards.as_ref().map_or(0, |hazards| hazards.eruption_radius.ceil() as usize);
+    cleanup + (1 + flow).max(eruption)
+}
+
+impl ChunkedWorld {
+    pub fn new(args: &Args, params: &WorldParameters, capacity: usize) -> Result<ChunkedWorld, String> {
+        let scale = (CALIBRATION_SIDE / args.x.max(args.y) as f64).min(1.0);
+        let guides = Arc::new(AltitudeGuides::load(args, params)?);
+        let mut sample = WorldBuilder::new(args, params, &guides)?;
+        sample.region(0.0, 0.0, args.x as f64, args.y as f64, scale).calibrate();
+
+        let mut params = sample.params.clone();
+        params.target_land_ratio = None;
+        params.hills_q = None;
+        params.mountain_q = None;
+        params.peak_q = None;
+        let halo = halo(&params);
+        println!("[MapGen] Chunks are {} tiles wide with a {} tile halo.", CHUNK_SIZE, halo);
+
+        Ok(ChunkedWorld {
+            params,
+            seed: sample.seed.clone(),
+            noise: sample.params.noise.build(sample.seeds),
+            guides,
+            normalization: sample.metadata.normalization.clone(),
+            extent: (args.x as f64, args.y as f64),
+            halo,
+            capacity: capacity.max(1),
+            chunks: HashMap::new(),
+            recent: VecDeque::new(),
+        })
+    }
+
+    pub fn params(&self) -> &WorldParameters { &self.params }
+
+    pub fn loaded(&self) -> usize { self.chunks.len() }
+
+    pub fn chunk(&mut self, cx: i64, cy: i64) -> &Chunk {
+        if self.chunks.contains_key(&(cx, cy)) {
+            self.recent.retain(|key| *key != (cx, cy));
+        } else {
+            let chunk = self.generate(cx, cy);
+            self.chunks.insert((cx, cy), chunk);
+            while self.chunks.len() > self.capacity {
+                if let Some(key) = self.recent.pop_front() {
+                    self.chunks.remove(&key);
+                }
+            }
+        }
+        self.recent.push_back((cx, cy));
+        &self.chunks[&(cx, cy)]
+    }
+
+    pub fn tile(&mut self, x: i64, y: i64) -> &Tile {
+        let size = CHUNK_SIZE as i64;
+        let (cx, cy) = (x.div_euclid(size), y.div_euclid(size));
+        let local = (x.rem_euclid(size) + y.rem_euclid(size) * size) as usize;
+        &self.chunk(cx, cy).tiles[local]
+    }
+
+    /// Runs the stages of `WorldBuilder::build` after calibration, in the same
+    /// order, over the chunk and its halo.
+    fn generate(&self, cx: i64, cy: i64) -> Chunk {
+        let padded = CHUNK_SIZE + 2 * self.halo;
+        let origin = (cx * CHUNK_SIZE as i64, cy * CHUNK_SIZE as i64);
+        let viewport = Viewport {
+            x: (origin.0 - self.halo as i64) as f64,
+            y: (origin.1 - self.halo as i64) as f64,
+            scale: 1.0,
+            equator: self.extent.1 / 2.0,
+            extent: self.extent,
+        };
+        let params = &self.params;
+
+        let mut tiles = (0..padded * padded).into_par_iter().map(|i| {
+            let mut tile = Tile::new(i, (i % padded) as f64, (i / padded) as f64, &viewport, &self.noise, params, &self.guides);
+            if !self.normalization.is_empty() {
+                self.normalization.iter().for_each(|record| record.apply(&mut tile));
+                tile.classify(params);
+            }
+            tile
+        }).collect::<Vec<Tile>>();
+
+        cleanup(&mut tiles, padded, params, 1.0);
+        raise_volcanoes(&mut tiles, &viewport, params, self.seed.derive("volcanism"), self.extent);
+        mark_coast(&mut tiles, padded);
+        generate_ice(&mut tiles, padded, params);
+        populate(&mut tiles, padded);
+        assess(&mut tiles, &viewport, params, self.seed.derive("faults"));
+
+        let mut core = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE);
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let mut tile = tiles[(x + self.halo) + (y + self.halo) * padded].clone();
+                tile.id = x + y * CHUNK_SIZE;
+                tile.x = (origin.0 + x as i64) as f64;
+                tile.y = (origin.1 + y as i64) as f64;
+                core.push(tile);
+            }
+        }
+
+        Chunk { cx, cy, tiles: core }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use crate::map::{cleanup::CleanupParameters, presets::load_preset};
+    use super::*;
+
+    #[test]
+    fn chunks_match_a_single_world() {
+        let args = Args::parse_from(["civ-sim", "-x", "256", "-y", "256", "-s", "chunks"]);
+        let mut params = load_preset("earthlike").unwrap();
+        params.cleanup = Some(CleanupParameters {
+            min_island_area: 6,
+            min_lake_area: 6,
+            smoothing_passes: 2,
+            birth: 6,
+            survival: 3,
+        });
+        let world = WorldBuilder::new(&args, &params, &Arc::new(AltitudeGuides::load(&args, &params).unwrap())).unwrap().build();
+        let mut chunked = ChunkedWorld::new(&args, &params, 2).unwrap();
+        let margin = chunked.halo as i64;
+
+        for y in margin..256 - margin {
+            for x in margin..256 - margin {
+                let expected = &world.tiles[x as usize + y as usize * 256];
+                let tile = chunked.tile(x, y);
+                assert_eq!(tile.biome, expected.biome, "biome at ({}, {})", x, y);
+                assert_eq!(tile.altitude, expected.altitude, "altitude at ({}, {})", x, y);
+                assert_eq!(tile.ice.map(|ice| ice.thickness), expected.ice.map(|ice| ice.thickness), "ice at ({}, {})", x, y);
+                assert_eq!(tile.volcano, expected.volcano, "volcano at ({}, {})", x, y);
+                assert_eq!(tile.risk, expected.risk, "risk at ({}, {})", x, y);
+                assert_eq!(tile.fauna.capacity, expected.fauna.capacity, "fauna at ({}, {})", x, y);
+            }
+        }
+        assert!(chunked.loaded() <= 2);
+    }
+
+    #[test]
+    fn huge_extents_only_hold_the_sample_and_chunks() {
+        let args = Args::parse_from(["civ-sim", "-x", "1000000", "-y", "1000000", "-s", "huge"]);
+        let params = load_preset("earthlike").unwrap();
+        let mut chunked = ChunkedWorld::new(&args, &params, 1).unwrap();
+        let chunk = chunked.chunk(4000, 3000);
+        assert_eq!(chunk.tiles.len(), CHUNK_SIZE * CHUNK_SIZE);
+        assert_eq!((chunk.tiles[0].x, chunk.tiles[0].y), (4000.0 * CHUNK_SIZE as f64, 3000.0 * CHUNK_SIZE as f64));
+    }
+}
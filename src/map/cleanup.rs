@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use crate::{
+    map::{tile::Tile, world::WorldParameters},
+    utils::helpers::{neighbors8, regions}
+};
+
+/// How far past the sea level a flipped tile is moved, so it classifies cleanly.
+const NUDGE: f64 = 0.01;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct CleanupParameters {
+    /// Islands with fewer tiles than this are sunk
+    #[serde(default)]
+    pub min_island_area: usize,
+    /// Lakes and inlets with fewer tiles than this are filled
+    #[serde(default)]
+    pub min_lake_area: usize,
+    /// Number of cellular automata passes over the coastline
+    #[serde(default)]
+    pub smoothing_passes: usize,
+    /// A sea tile becomes land with at least this many land tiles among its 8 neighbors
+    #[serde(default = "default_birth")]
+    pub birth: usize,
+    /// A land tile stays land only with at least this many land tiles among its 8 neighbors
+    #[serde(default = "default_survival")]
+    pub survival: usize,
+}
+
+fn default_birth() -> usize { 6 }
+fn default_survival() -> usize { 3 }
+
+fn set_land(tile: &mut Tile, land: bool, sea_level: f64) {
+    tile.altitude = if land {
+        tile.altitude.max(sea_level + NUDGE)
+    } else {
+        tile.altitude.min(sea_level - NUDGE)
+    };
+}
+
+/// Smooths the coastline and removes small islands and lakes by moving tile
+/// altitudes across the sea level. `area_scale` multiplies the minimum areas,
+/// so zoomed regions remove features of the same size in world units. Islands
+/// and lakes touching the map edge are always kept.
+/// Returns the number of tiles that changed between land and sea.
+pub fn cleanup(
+    tiles: &mut [Tile],
+    width: usize,
+    params: &WorldParameters,
+    area_scale: f64
+) -> usize {
+    let cleanup = match &params.cleanup {
+        Some(cleanup) => cleanup,
+        None => return 0,
+    };
+    let size = tiles.len();
+    let sea_level = params.sea_level;
+    let original = tiles.iter().map(|tile| tile.altitude > sea_level).collect::<Vec<bool>>();
+    let mut land = original.clone();
+
+    for _ in 0..cleanup.smoothing_passes {
+        let next = (0..size).map(|i| {
+            let neighbors = neighbors8(i, width, size);
+            // Off-map neighbors count as the same as the tile itself
+            let count = neighbors.iter().filter(|n| land[**n]).count()
+                + if land[i] { 8 - neighbors.len() } else { 0 };
+            if land[i] { count >= cleanup.survival } else { count >= cleanup.birth }
+        }).collect::<Vec<bool>>();
+        if next == land { break; }
+        land = next;
+    }
+
+    // Regions cut by the map edge may go on past it, as open ocean does in a
+    // zoomed region, so only those seen whole are measured
+    let height = size / width;
+    let on_edge = |i: &usize| {
+        let (x, y) = (i % width, i / width);
+        x == 0 || y == 0 || x == width - 1 || y == height - 1
+    };
+    let min_island = (cleanup.min_island_area as f64 * area_scale).round() as usize;
+    let min_lake = (cleanup.min_lake_area as f64 * area_scale).round() as usize;
+    for region in regions(width, size, |i| land[i]) {
+        if region.len() < min_island && !region.iter().any(on_edge) {
+            region.iter().for_each(|i| land[*i] = false);
+        }
+    }
+    for region in regions(width, size, |i| !land[i]) {
+        if region.len() < min_lake && !region.iter().any(on_edge) {
+            region.iter().for_each(|i| land[*i] = true);
+        }
+    }
+
+    let mut changed = 0;
+    for i in 0..size {
+        if land[i] != original[i] {
+            set_land(&mut tiles[i], land[i], sea_level);
+            tiles[i].classify(params);
+            changed += 1;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::{guide::AltitudeGuides, presets::load_preset, world::Viewport};
+    use super::*;
+
+    /// All land, with sea wherever `sea` holds.
+    fn grid(width: usize, height: usize, params: &WorldParameters, sea: impl Fn(usize, usize) -> bool) -> Vec<Tile> {
+        let noise = [0, 1, 2, 3].map(noise::Fbm::<noise::SuperSimplex>::new);
+        let viewport = Viewport { x: 0.0, y: 0.0, scale: 1.0, equator: height as f64 / 2.0, extent: (width as f64, height as f64) };
+        (0..width * height).map(|i| {
+            let (x, y) = (i % width, i / width);
+            let mut tile = Tile::new(i, x as f64, y as f64, &viewport, &noise, params, &AltitudeGuides::default());
+            tile.altitude = if sea(x, y) { -0.5 } else { 0.5 };
+            tile.classify(params);
+            tile
+        }).collect()
+    }
+
+    #[test]
+    fn small_regions_on_the_map_edge_are_kept() {
+        let mut params = load_preset("earthlike").unwrap();
+        params.cleanup = Some(CleanupParameters {
+            min_island_area: 0,
+            min_lake_area: 5,
+            smoothing_passes: 0,
+            birth: default_birth(),
+            survival: default_survival(),
+        });
+        // A two-tile lake inside the map and a two-tile inlet cut by its left edge
+        let mut tiles = grid(10, 10, &params, |x, y| (y == 5 && (x == 4 || x == 5)) || (y == 2 && x <= 1));
+        let changed = cleanup(&mut tiles, 10, &params, 1.0);
+
+        assert_eq!(changed, 2);
+        assert!(!tiles[4 + 5 * 10].is_sea() && !tiles[5 + 5 * 10].is_sea());
+        assert!(tiles[2 * 10].is_sea() && tiles[1 + 2 * 10].is_sea());
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::map::{
+    fauna,
+    hazards::assess,
+    ice::generate_ice,
+    tile::Biome,
+    world::World
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForcingKind {
+    /// Raises `global_heat_scaling`, cooling high latitudes the most
+    IceAge,
+    /// Lowers `global_heat_scaling`
+    WarmPeriod,
+    /// Lowers rainfall, everywhere or within `region`
+    Megadrought,
+}
+
+/// One episode of the climate timeline. It ramps in over the first quarter of
+/// its duration, holds, and ramps out over the last quarter.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Forcing {
+    pub kind: ForcingKind,
+    /// Simulation year the episode begins
+    pub start: u32,
+    pub duration: u32,
+    /// Change to `global_heat_scaling` for ice ages and warm periods, to rainfall for megadroughts
+    pub strength: f64,
+    /// Center x, center y and radius as fractions of the map, like guide shapes;
+    /// the radius is relative to the shorter side. Megadroughts only
+    #[serde(default)]
+    pub region: Option<[f64; 3]>,
+}
+
+impl Forcing {
+    /// How far the episode is in effect at `year`, from 0.0 to 1.0.
+    fn envelope(&self, year: u32) -> f64 {
+        if year < self.start || year >= self.start + self.duration { return 0.0; }
+        let ramp = (self.duration as f64 / 4.0).max(1.0);
+        let elapsed = (year - self.start) as f64;
+        let remaining = (self.start + self.duration - year) as f64;
+        (elapsed / ramp).min(remaining / ramp).min(1.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClimateRecord {
+    pub year: u32,
+    pub heat_scaling: f64,
+    pub mean_temperature: f64,
+    pub mean_rainfall: f64,
+    /// Tiles whose biome changed this year
+    pub reclassified: usize,
+    /// Share of tiles per biome, from 0.0 to 1.0
+    pub biomes: BTreeMap<Biome, f64>,
+}
+
+/// Temperature and rainfall as generated, which every forcing is applied to,
+/// and the climate of every simulated year with a forcing in effect.
+#[derive(Clone, Default, Serialize)]
+pub struct ClimateHistory {
+    #[serde(skip)]
+    baseline: Vec<(f64, f64)>,
+    /// The forcing applied last, so unchanged years are skipped
+    #[serde(skip)]
+    applied: Option<Vec<f64>>,
+    pub records: Vec<ClimateRecord>,
+}
+
+impl World {
+    /// Steps the climate through every year after `from` up to the current
+    /// year, then brings ice, game habitat and hazard risk in line if it changed.
+    pub fn advance_climate(&mut self, from: u32) {
+        let changed = (from + 1..=self.year).fold(false, |changed, year| self.step_climate(year) | changed);
+        if changed { self.refresh_climate(); }
+    }
+
+    /// Applies the climate timeline for `year` to every tile's
+    /// temperature and rainfall and reclassifies land whose biome changes.
+    /// Sea and coast keep their biome, as altitude never changes.
+    /// Shifts are applied to the stored fields, after any normalization.
+    /// Returns whether the climate changed.
+    fn step_climate(&mut self, year: u32) -> bool {
+        let timeline = match &self.params.climate {
+            Some(timeline) => timeline,
+            None => return false,
+        };
+        let envelopes = timeline.iter().map(|forcing| forcing.envelope(year)).collect::<Vec<f64>>();
+        let unchanged = self.climate.applied.as_ref().map_or(envelopes.iter().all(|e| *e == 0.0), |applied| *applied == envelopes);
+        if unchanged { return false; }
+
+        if self.climate.baseline.is_empty() {
+            self.climate.baseline = self.tiles.iter().map(|tile| (tile.temperature, tile.rainfall)).collect();
+        }
+
+        let base = self.params.global_heat_scaling;
+        let heat_scaling = base + timeline.iter().zip(&envelopes).map(|(forcing, envelope)| match forcing.kind {
+            ForcingKind::IceAge => forcing.strength * envelope,
+            ForcingKind::WarmPeriod => -forcing.strength * envelope,
+            ForcingKind::Megadrought => 0.0,
+        }).sum::<f64>();
+
+        let mut reclassified = 0;
+        for (tile, (temperature, rainfall)) in self.tiles.iter_mut().zip(&self.climate.baseline) {
+            let (sx, sy) = self.viewport.sample(tile.x, tile.y);
+            // Same latitude term and temperature feedback on rainfall as `Tile::new`
+            tile.temperature = temperature - tile.latitude.abs() * 8.0 * (heat_scaling - base) / 10.0;
+            let drought = timeline.iter().zip(&envelopes)
+                .filter(|(forcing, _)| forcing.kind == ForcingKind::Megadrought)
+                .map(|(forcing, envelope)| {
+                    let falloff = forcing.region.map_or(1.0, |[x, y, radius]| {
+                        let (width, height) = self.viewport.extent;
+                        let distance = ((sx - x * width).powi(2) + (sy - y * height).powi(2)).sqrt();
+                        (1.0 - distance / (radius * width.min(height))).max(0.0)
+                    });
+                    forcing.strength * envelope * falloff
+                })
+                .sum::<f64>();
+            tile.rainfall = rainfall + (tile.temperature.abs() - temperature.abs()) / 3.0 - drought;
+
+            if tile.is_sea() || tile.biome == Biome::Coast { continue; }
+            let previous = tile.biome;
+            tile.classify(&self.params);
+            if tile.biome != previous { reclassified += 1; }
+        }
+
+        let size = self.size as f64;
+        let mut counts = BTreeMap::<Biome, usize>::new();
+        self.tiles.iter().for_each(|tile| *counts.entry(tile.biome).or_insert(0) += 1);
+        let record = ClimateRecord {
+            year,
+            heat_scaling,
+            mean_temperature: self.tiles.iter().map(|tile| tile.temperature).sum::<f64>() / size,
+            mean_rainfall: self.tiles.iter().map(|tile| tile.rainfall).sum::<f64>() / size,
+            reclassified,
+            biomes: counts.into_iter().map(|(biome, count)| (biome, count as f64 / size)).collect(),
+        };
+        if reclassified > 0 {
+            println!("[MapGen] Year {}: heat scaling {:.3}, {} tiles changed biome.", year, heat_scaling, reclassified);
+        }
+        self.climate.records.push(record);
+        self.climate.applied = Some(envelopes);
+        true
+    }
+
+    /// Brings ice, game habitat and hazard risk in line with a changed climate.
+    /// Strategic features only depend on altitude and sea, so they are kept.
+    fn refresh_climate(&mut self) {
+        println!("[MapGen] Updating ice, fauna and hazards for the new climate.");
+        generate_ice(&mut self.tiles, self.width, &self.params);
+        fauna::refit(&mut self.tiles, self.width);
+        assess(&mut self.tiles, &self.viewport, &self.params, self.seed.derive("faults"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use clap::Parser;
+    use crate::{
+        map::{guide::AltitudeGuides, presets::load_preset, world::WorldBuilder},
+        utils::cli::Args
+    };
+    use super::*;
+
+    #[test]
+    fn a_regional_drought_only_reclassifies_tiles_it_reaches() {
+        let args = Args::parse_from(["civ-sim", "-x", "80", "-y", "60", "-s", "drought"]);
+        let mut params = load_preset("earthlike").unwrap();
+        let region = [0.5, 0.5, 0.25];
+        params.climate = Some(vec![Forcing { kind: ForcingKind::Megadrought, start: 1, duration: 8, strength: 0.5, region: Some(region) }]);
+        let guides = Arc::new(AltitudeGuides::load(&args, &params).unwrap());
+        let mut world = WorldBuilder::new(&args, &params, &guides).unwrap().build();
+        let before = world.tiles.iter().map(|tile| (tile.temperature, tile.rainfall, tile.biome)).collect::<Vec<_>>();
+
+        assert!(world.step_climate(3));
+        assert_eq!(world.climate.records.len(), 1);
+
+        let [x, y, radius] = region;
+        let (width, height) = world.viewport.extent;
+        let mut changed = 0;
+        for (tile, (temperature, rainfall, biome)) in world.tiles.iter().zip(before) {
+            let distance = ((tile.x - x * width).powi(2) + (tile.y - y * height).powi(2)).sqrt();
+            if distance >= radius * width.min(height) {
+                assert_eq!((tile.temperature, tile.rainfall, tile.biome), (temperature, rainfall, biome));
+            }
+            if tile.is_sea() || biome == Biome::Coast { assert_eq!(tile.biome, biome); }
+            if tile.biome != biome { changed += 1; }
+        }
+        assert!(changed > 0);
+        assert_eq!(world.climate.records[0].reclassified, changed);
+
+        // Holding the same forcing the next year changes nothing and records nothing
+        assert!(!world.step_climate(4));
+        assert_eq!(world.climate.records.len(), 1);
+    }
+}
+
@@ -0,0 +1,142 @@
+use std::fmt::{Display, Formatter, Result};
+use serde::Serialize;
+use crate::{
+    map::tile::{Biome, Tile},
+    utils::helpers::adjacent
+};
+
+/// Share of capacity that wanders back into a tile emptied of a species.
+const RECOLONIZATION: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Species {
+    /// Grazing herds of open country: bison, horses, reindeer
+    Grazers,
+    /// Deer, boar and other woodland game
+    ForestGame,
+    /// Spawning fish crowding shores and wetlands
+    FishRuns,
+    /// Seals, walrus and whales of cold seas
+    MarineMammals,
+}
+
+impl Species {
+    pub const ALL: [Species; 4] = [Species::Grazers, Species::ForestGame, Species::FishRuns, Species::MarineMammals];
+
+    /// Yearly growth rate of a population well below its carrying capacity.
+    pub fn regrowth(&self) -> f64 {
+        match self {
+            Species::Grazers => 0.3,
+            Species::ForestGame => 0.4,
+            Species::FishRuns => 0.8,
+            Species::MarineMammals => 0.1,
+        }
+    }
+
+    /// How well a biome suits the species, from 0.0 to 1.0.
+    fn habitat(&self, biome: Biome) -> f64 {
+        match (self, biome) {
+            (Species::Grazers, Biome::Plains) => 1.0,
+            (Species::Grazers, Biome::Tundra | Biome::Hill) => 0.6,
+            (Species::Grazers, Biome::Temperate) => 0.5,
+            (Species::Grazers, Biome::Coast) => 0.4,
+            (Species::Grazers, Biome::Boreal | Biome::Wetland) => 0.3,
+            (Species::Grazers, Biome::Desert | Biome::Rainforest) => 0.1,
+            (Species::ForestGame, Biome::Temperate) => 1.0,
+            (Species::ForestGame, Biome::Rainforest) => 0.9,
+            (Species::ForestGame, Biome::Boreal) => 0.8,
+            (Species::ForestGame, Biome::Wetland) => 0.5,
+            (Species::ForestGame, Biome::Hill) => 0.4,
+            (Species::ForestGame, Biome::Plains | Biome::Mountain | Biome::Coast) => 0.2,
+            (Species::FishRuns, Biome::Coast) => 1.0,
+            (Species::FishRuns, Biome::Wetland) => 0.6,
+            (Species::FishRuns, Biome::Sea) => 0.5,
+            (Species::MarineMammals, Biome::Sea) => 1.0,
+            (Species::MarineMammals, Biome::Coast) => 0.3,
+            _ => 0.0,
+        }
+    }
+
+    /// Carrying capacity of a tile, from 0.0 to 1.0. `shore` is whether the
+    /// tile touches the other of land and sea.
+    fn capacity(&self, tile: &Tile, shore: bool) -> f64 {
+        let habitat = self.habitat(tile.biome) * (1.0 - tile.blend) + self.habitat(tile.ecotone) * tile.blend;
+        let ice = tile.ice.map_or(0.0, |ice| ice.thickness);
+        let wetness = ((tile.rainfall + 1.0) / 2.0).clamp(0.0, 1.0);
+        let cold = (-tile.temperature).clamp(0.0, 1.0);
+        let capacity = match self {
+            Species::Grazers | Species::ForestGame => habitat * tile.fertility * (1.0 - ice),
+            // Runs need streams to spawn in and a shore to gather at
+            Species::FishRuns if shore => habitat * (0.5 + 0.5 * wetness),
+            Species::FishRuns => 0.0,
+            // Cold, ice-edged seas are the richest
+            Species::MarineMammals => habitat * cold.max(ice),
+        };
+        capacity.clamp(0.0, 1.0)
+    }
+}
+
+impl Display for Species {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Species::Grazers => write!(f, "grazers"),
+            Species::ForestGame => write!(f, "forest game"),
+            Species::FishRuns => write!(f, "fish runs"),
+            Species::MarineMammals => write!(f, "marine mammals"),
+        }
+    }
+}
+
+/// Game on a tile, indexed like `Species::ALL`.
+#[derive(Debug, Clone, Default)]
+pub struct Fauna {
+    /// Population the tile supports, from 0.0 to 1.0
+    pub capacity: [f64; 4],
+    /// Current population, from 0.0 to `capacity`
+    pub density: [f64; 4],
+}
+
+impl Fauna {
+    pub fn density(&self, species: Species) -> f64 {
+        self.density[species as usize]
+    }
+
+    /// Logistic regrowth towards capacity over `years`. Emptied tiles are
+    /// slowly recolonized from a small fraction of their capacity.
+    pub fn regrow(&mut self, years: f64) {
+        for species in Species::ALL {
+            let (s, capacity) = (species as usize, self.capacity[species as usize]);
+            if capacity <= 0.0 { continue; }
+            let density = self.density[s].max(RECOLONIZATION * capacity);
+            self.density[s] = (density + species.regrowth() * years * density * (1.0 - density / capacity)).min(capacity);
+        }
+    }
+}
+
+fn capacities(tiles: &[Tile], width: usize) -> Vec<[f64; 4]> {
+    let size = tiles.len();
+    (0..size).map(|i| {
+        let shore = adjacent(i, width, size).iter().any(|n| tiles[*n].is_sea() != tiles[i].is_sea());
+        Species::ALL.map(|species| species.capacity(&tiles[i], shore))
+    }).collect()
+}
+
+/// Sets the carrying capacity of every tile from its biome, climate, ice and
+/// soil, and starts each population at capacity.
+pub fn populate(tiles: &mut [Tile], width: usize) {
+    let capacities = capacities(tiles, width);
+    tiles.iter_mut().zip(capacities).for_each(|(tile, capacity)| {
+        tile.fauna = Fauna { capacity, density: capacity };
+    });
+}
+
+/// Recomputes carrying capacities after the land changed, culling
+/// populations above the new capacity and leaving the rest to regrow.
+pub fn refit(tiles: &mut [Tile], width: usize) {
+    let capacities = capacities(tiles, width);
+    tiles.iter_mut().zip(capacities).for_each(|(tile, capacity)| {
+        tile.fauna.density.iter_mut().zip(capacity).for_each(|(density, capacity)| *density = density.min(capacity));
+        tile.fauna.capacity = capacity;
+    });
+}
@@ -0,0 +1,127 @@
+use serde::Serialize;
+use crate::{
+    map::{
+        tile::{Biome, Tile},
+        world::World
+    },
+    utils::helpers::regions
+};
+
+/// How far, in tiles, the detectors look for barriers on either side.
+const REACH: i64 = 4;
+/// Minimum fraction of rays that must hit land for a bay to count as sheltered.
+const SHELTER: f64 = 0.625;
+
+const AXES: [(i64, i64); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+const RAYS: [(i64, i64); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (-1, -1), (1, -1), (-1, 1)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeatureKind {
+    /// Passable land squeezed between mountains
+    Pass,
+    /// Narrow land bridge between sea on both sides
+    Isthmus,
+    /// Narrow sea channel between land on both sides
+    Strait,
+    /// Sea tile by the shore, enclosed by land in most directions
+    Harbor,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategicFeature {
+    pub kind: FeatureKind,
+    pub tiles: Vec<usize>,
+    /// From 0.0 to 1.0; higher is more pronounced
+    pub score: f64,
+}
+
+fn is_high(tile: &Tile) -> bool {
+    matches!(tile.biome, Biome::Mountain | Biome::Peak)
+}
+
+fn tile_at(world: &World, x: i64, y: i64) -> Option<&Tile> {
+    if x < 0 || y < 0 || x >= world.width as i64 || y >= world.height as i64 { return None; }
+    Some(&world.tiles[x as usize + y as usize * world.width])
+}
+
+/// Distance along (dx, dy) to the first tile matching `found`, within `reach`.
+fn probe(world: &World, x: i64, y: i64, (dx, dy): (i64, i64), reach: i64, found: impl Fn(&Tile) -> bool) -> Option<i64> {
+    (1..=reach).find(|k| tile_at(world, x + dx * k, y + dy * k).is_some_and(&found))
+}
+
+/// Whether every tile along (dx, dy) up to `reach` exists and matches `keep`.
+fn continues(world: &World, x: i64, y: i64, (dx, dy): (i64, i64), reach: i64, keep: impl Fn(&Tile) -> bool) -> bool {
+    (1..=reach).all(|k| tile_at(world, x + dx * k, y + dy * k).is_some_and(&keep))
+}
+
+/// Narrowest crossing through (x, y): `barrier` must be reached on both sides
+/// of one axis while `corridor` continues on both sides of the perpendicular one.
+/// Returns the score of the narrowest such axis.
+fn narrows(
+    world: &World,
+    x: i64,
+    y: i64,
+    barrier: impl Fn(&Tile) -> bool + Copy,
+    corridor: impl Fn(&Tile) -> bool + Copy,
+    corridor_reach: i64
+) -> Option<f64> {
+    AXES.iter().filter_map(|&(dx, dy)| {
+        let a = probe(world, x, y, (dx, dy), REACH, barrier)?;
+        let b = probe(world, x, y, (-dx, -dy), REACH, barrier)?;
+        let (px, py) = (-dy, dx);
+        if !continues(world, x, y, (px, py), corridor_reach, corridor)
+            || !continues(world, x, y, (-px, -py), corridor_reach, corridor) { return None; }
+        Some(1.0 - (a + b - 2) as f64 / (2 * REACH - 1) as f64)
+    }).reduce(f64::max)
+}
+
+fn tile_score(world: &World, tile: &Tile) -> Option<(FeatureKind, f64)> {
+    let (x, y) = (tile.x as i64, tile.y as i64);
+
+    if tile.is_sea() {
+        if let Some(score) = narrows(world, x, y, |t| !t.is_sea(), |t| t.is_sea(), REACH) {
+            return Some((FeatureKind::Strait, score));
+        }
+        let shore = RAYS.iter().any(|&ray| probe(world, x, y, ray, 1, |t| !t.is_sea()).is_some());
+        if shore {
+            let hits = RAYS.iter()
+                .filter(|&&ray| probe(world, x, y, ray, 2 * REACH, |t| !t.is_sea()).is_some())
+                .count();
+            let enclosure = hits as f64 / RAYS.len() as f64;
+            if enclosure >= SHELTER && hits < RAYS.len() {
+                return Some((FeatureKind::Harbor, enclosure));
+            }
+        }
+        return None;
+    }
+
+    if let Some(score) = narrows(world, x, y, |t| t.is_sea(), |t| !t.is_sea(), REACH) {
+        return Some((FeatureKind::Isthmus, score));
+    }
+    if !is_high(tile) {
+        let passable = |t: &Tile| !t.is_sea() && !is_high(t);
+        if let Some(score) = narrows(world, x, y, is_high, passable, 2) {
+            return Some((FeatureKind::Pass, score));
+        }
+    }
+    None
+}
+
+/// Finds passes, isthmuses, straits and harbors, grouping neighboring tiles
+/// of the same kind into one feature scored by its strongest tile.
+pub fn detect_features(world: &World) -> Vec<StrategicFeature> {
+    println!("[MapGen] Detecting strategic features.");
+    let scores = world.tiles.iter().map(|tile| tile_score(world, tile)).collect::<Vec<Option<(FeatureKind, f64)>>>();
+
+    let mut features = Vec::new();
+    for kind in [FeatureKind::Pass, FeatureKind::Isthmus, FeatureKind::Strait, FeatureKind::Harbor] {
+        let matches = |i: usize| scores[i].is_some_and(|(k, _)| k == kind);
+        for tiles in regions(world.width, world.size, matches) {
+            let score = tiles.iter().filter_map(|i| scores[*i].map(|(_, s)| s)).fold(0.0, f64::max);
+            features.push(StrategicFeature { kind, tiles, score });
+        }
+    }
+    println!("[MapGen] Found {} strategic features.", features.len());
+    features
+}
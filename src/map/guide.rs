@@ -0,0 +1,257 @@
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use nannou::image;
+use crate::{
+    map::{heightmap::Heightmap, world::WorldParameters},
+    utils::cli::Args
+};
+
+/// Coarse land/sea layout from the parameters file. Coordinates are fractions
+/// of the world size, so (0.0, 0.0) is the top-left corner and (1.0, 1.0) the
+/// bottom-right one.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LandGuide {
+    /// Grayscale image where white means land and black means sea
+    #[serde(default)]
+    pub mask: Option<String>,
+    #[serde(default)]
+    pub shapes: Vec<Shape>,
+    /// How far the altitude is pushed towards the guide value
+    #[serde(default = "default_strength")]
+    pub strength: f64,
+    /// Guide value outside every shape when no mask is given (-1.0 is sea)
+    #[serde(default = "default_background")]
+    pub background: f64,
+    /// Width of the soft edge around shapes
+    #[serde(default = "default_falloff")]
+    pub falloff: f64,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Shape {
+    Ellipse {
+        x: f64,
+        y: f64,
+        rx: f64,
+        ry: f64,
+        #[serde(default = "default_value")]
+        value: f64,
+    },
+    Polygon {
+        points: Vec<[f64; 2]>,
+        #[serde(default = "default_value")]
+        value: f64,
+    },
+}
+
+fn default_strength() -> f64 { 0.4 }
+fn default_background() -> f64 { -1.0 }
+fn default_falloff() -> f64 { 0.05 }
+fn default_value() -> f64 { 1.0 }
+
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+impl Shape {
+    fn value(&self) -> f64 {
+        match self {
+            Shape::Ellipse { value, .. } => *value,
+            Shape::Polygon { value, .. } => *value,
+        }
+    }
+
+    /// Approximate signed distance to the shape edge; negative inside.
+    fn signed_distance(&self, u: f64, v: f64) -> f64 {
+        match self {
+            Shape::Ellipse { x, y, rx, ry, .. } => {
+                let d = (((u - x) / rx).powi(2) + ((v - y) / ry).powi(2)).sqrt();
+                (d - 1.0) * rx.min(*ry)
+            },
+            Shape::Polygon { points, .. } => {
+                if points.len() < 3 { return f64::MAX; }
+                let mut inside = false;
+                let mut distance = f64::MAX;
+                for i in 0..points.len() {
+                    let [ax, ay] = points[i];
+                    let [bx, by] = points[(i + 1) % points.len()];
+                    if (ay > v) != (by > v) && u < (bx - ax) * (v - ay) / (by - ay) + ax {
+                        inside = !inside;
+                    }
+                    let (ex, ey) = (bx - ax, by - ay);
+                    let t = (((u - ax) * ex + (v - ay) * ey) / (ex * ex + ey * ey)).clamp(0.0, 1.0);
+                    distance = distance.min(((u - ax - t * ex).powi(2) + (v - ay - t * ey).powi(2)).sqrt());
+                }
+                if inside { -distance } else { distance }
+            }
+        }
+    }
+}
+
+/// Pixels of a land mask image, from -1.0 (black) to 1.0 (white).
+struct MaskImage {
+    path: String,
+    width: usize,
+    height: usize,
+    values: Vec<f64>,
+}
+
+impl MaskImage {
+    fn load(path: &str) -> Result<MaskImage, String> {
+        println!("[MapGen] Loading land mask from {}", path);
+        let img = image::open(path)
+            .map_err(|e| format!("[MapGen] Failed to load land mask '{}': {}", path, e))?
+            .to_luma8();
+        let values = img.pixels()
+            .map(|pixel| (pixel[0] as f64 / 255.0) * 2.0 - 1.0)
+            .collect::<Vec<f64>>();
+        Ok(MaskImage { path: path.to_string(), width: img.width() as usize, height: img.height() as usize, values })
+    }
+}
+
+/// Runtime form of a `LandGuide`, with the mask image already loaded.
+pub struct LandMask {
+    guide: LandGuide,
+    mask: Option<Arc<MaskImage>>,
+    width: f64,
+    height: f64,
+}
+
+impl LandMask {
+    pub fn new(guide: &LandGuide, width: usize, height: usize) -> Result<LandMask, String> {
+        Ok(LandMask {
+            guide: guide.clone(),
+            mask: guide.mask.as_deref().map(MaskImage::load).transpose()?.map(Arc::new),
+            width: width as f64,
+            height: height as f64,
+        })
+    }
+
+    /// Same world size with the settings of `guide`. The mask image is only
+    /// read again if `guide` names a different one.
+    pub fn refit(&self, guide: &LandGuide) -> Result<LandMask, String> {
+        let mask = match (&self.mask, &guide.mask) {
+            (Some(mask), Some(path)) if mask.path == *path => Some(mask.clone()),
+            (_, path) => path.as_deref().map(MaskImage::load).transpose()?.map(Arc::new),
+        };
+        Ok(LandMask { guide: guide.clone(), mask, width: self.width, height: self.height })
+    }
+
+    /// Bilinear sample of the mask image at normalized coordinates.
+    fn sample_mask(&self, u: f64, v: f64) -> Option<f64> {
+        let MaskImage { width: w, height: h, values, .. } = self.mask.as_deref()?;
+        let fx = (u * *w as f64 - 0.5).clamp(0.0, (*w - 1) as f64);
+        let fy = (v * *h as f64 - 0.5).clamp(0.0, (*h - 1) as f64);
+        let (x0, y0) = (fx as usize, fy as usize);
+        let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+        let (tx, ty) = (fx - x0 as f64, fy - y0 as f64);
+        let top = values[x0 + w * y0] * (1.0 - tx) + values[x1 + w * y0] * tx;
+        let bottom = values[x0 + w * y1] * (1.0 - tx) + values[x1 + w * y1] * tx;
+        Some(top * (1.0 - ty) + bottom * ty)
+    }
+
+    /// Guide value in [-1.0, 1.0] at tile coordinates (x, y).
+    pub fn value(&self, x: f64, y: f64) -> f64 {
+        let (u, v) = (x / self.width, y / self.height);
+        let half = self.guide.falloff / 2.0;
+        self.guide.shapes.iter().fold(
+            self.sample_mask(u, v).unwrap_or(self.guide.background),
+            |value, shape| {
+                let coverage = 1.0 - smoothstep(-half, half, shape.signed_distance(u, v));
+                value * (1.0 - coverage) + shape.value() * coverage
+            }
+        )
+    }
+
+    pub fn bias(&self, x: f64, y: f64) -> f64 {
+        self.guide.strength * self.value(x, y)
+    }
+}
+
+/// Everything that overrides or biases the noise altitude in `Tile::new`.
+/// Loaded once and shared, as reading and resizing the images is slow.
+#[derive(Default)]
+pub struct AltitudeGuides {
+    pub heightmap: Option<Arc<Heightmap>>,
+    pub mask: Option<LandMask>,
+}
+
+impl AltitudeGuides {
+    /// Loads the `--heightmap` image and the land guide of `params`.
+    pub fn load(args: &Args, params: &WorldParameters) -> Result<AltitudeGuides, String> {
+        let heightmap = args.heightmap.as_ref()
+            .map(|path| Heightmap::load(path, args.x, args.y, args.heightmap_blend).map(Arc::new))
+            .transpose()?;
+        AltitudeGuides { heightmap, mask: None }.refit(args, params)
+    }
+
+    /// The same heightmap with the land guide of `params`, for parameters
+    /// that changed after loading. A mask image already loaded is reused.
+    pub fn refit(&self, args: &Args, params: &WorldParameters) -> Result<AltitudeGuides, String> {
+        let mask = match (&self.mask, &params.guide) {
+            (Some(mask), Some(guide)) => Some(mask.refit(guide)?),
+            (None, Some(guide)) => Some(LandMask::new(guide, args.x, args.y)?),
+            (_, None) => None,
+        };
+        Ok(AltitudeGuides { heightmap: self.heightmap.clone(), mask })
+    }
+
+    pub fn apply(&self, altitude: f64, x: f64, y: f64) -> f64 {
+        let altitude = match &self.heightmap {
+            Some(heightmap) => heightmap.apply(altitude, x, y),
+            None => altitude
+        };
+        match &self.mask {
+            Some(mask) => altitude + mask.bias(x, y),
+            None => altitude
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use nannou::image::{GrayImage, Luma};
+    use crate::map::presets::load_preset;
+    use super::*;
+
+    #[test]
+    fn missing_heightmap_is_an_error() {
+        let args = Args::parse_from(["civ-sim", "--heightmap", "/nope.png"]);
+        let params = load_preset("earthlike").unwrap();
+        let error = AltitudeGuides::load(&args, &params).err().unwrap();
+        assert!(error.contains("/nope.png"), "{}", error);
+    }
+
+    #[test]
+    fn refit_keeps_the_loaded_heightmap() {
+        let path = std::env::temp_dir().join("civ-sim-refit-heightmap.png");
+        GrayImage::from_pixel(4, 4, Luma([200])).save(&path).unwrap();
+        let args = Args::parse_from(["civ-sim", "-x", "8", "-y", "8", "--heightmap", path.to_str().unwrap()]);
+
+        let guides = AltitudeGuides::load(&args, &load_preset("earthlike").unwrap()).unwrap();
+        let refitted = guides.refit(&args, &load_preset("pangaea").unwrap()).unwrap();
+        assert!(Arc::ptr_eq(guides.heightmap.as_ref().unwrap(), refitted.heightmap.as_ref().unwrap()));
+        assert!(guides.mask.is_none() && refitted.mask.is_some());
+    }
+
+    #[test]
+    fn refit_keeps_the_loaded_mask_image() {
+        let path = std::env::temp_dir().join("civ-sim-refit-mask.png");
+        GrayImage::from_pixel(4, 4, Luma([255])).save(&path).unwrap();
+        let mut params = load_preset("pangaea").unwrap();
+        let guide = params.guide.as_mut().unwrap();
+        guide.mask = Some(path.to_str().unwrap().to_string());
+        let mask = LandMask::new(guide, 8, 8).unwrap();
+
+        guide.strength = 0.9;
+        let refitted = mask.refit(guide).unwrap();
+        assert!(Arc::ptr_eq(mask.mask.as_ref().unwrap(), refitted.mask.as_ref().unwrap()));
+        assert_eq!(refitted.bias(4.0, 4.0), 0.9);
+
+        guide.mask = Some("/nope.png".to_string());
+        assert!(mask.refit(guide).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use std::fmt::{Display, Formatter, Result};
+use noise::NoiseFn;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::map::{
+    fauna::Species,
+    tile::{Biome, Tile},
+    volcanism::Volcano,
+    world::{Viewport, World, WorldParameters}
+};
+
+/// Tiles per hazard roll; each year every hazard gets one roll per this many tiles.
+const ROLL_AREA: usize = 400;
+/// Risk of being near a dormant volcano, relative to an active one.
+const DORMANT_RISK: f64 = 0.3;
+/// Drought risk of land already drier than `desert_r`, where little is at stake.
+const DESERT_RISK: f64 = 0.3;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct HazardParameters {
+    /// Land this far above sea level or less floods
+    #[serde(default = "default_flood_height")]
+    pub flood_height: f64,
+    /// Width of the band above `desert_r` prone to drought
+    #[serde(default = "default_drought_band")]
+    pub drought_band: f64,
+    /// Distance from a volcano that eruptions reach, in world units
+    #[serde(default = "default_eruption_radius")]
+    pub eruption_radius: f64,
+    /// Size of the fault line pattern, in world units
+    #[serde(default = "default_fault_scale")]
+    pub fault_scale: f64,
+    /// Width of fault lines, in noise units
+    #[serde(default = "default_fault_width")]
+    pub fault_width: f64,
+    /// Yearly chance of each roll firing on a tile at full risk, in `Hazard::ALL` order
+    #[serde(default = "default_rates")]
+    pub rates: [f64; 4],
+    /// Years for lost fertility to come back
+    #[serde(default = "default_recovery_years")]
+    pub recovery_years: f64,
+}
+
+fn default_flood_height() -> f64 { 0.08 }
+fn default_drought_band() -> f64 { 0.15 }
+fn default_eruption_radius() -> f64 { 10.0 }
+fn default_fault_scale() -> f64 { 400.0 }
+fn default_fault_width() -> f64 { 0.04 }
+fn default_rates() -> [f64; 4] { [0.05, 0.03, 0.02, 0.02] }
+fn default_recovery_years() -> f64 { 10.0 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hazard {
+    Flood,
+    Drought,
+    Eruption,
+    Earthquake,
+}
+
+impl Hazard {
+    pub const ALL: [Hazard; 4] = [Hazard::Flood, Hazard::Drought, Hazard::Eruption, Hazard::Earthquake];
+
+    /// Radius of one event, in world units.
+    fn reach(&self, params: &HazardParameters) -> f64 {
+        match self {
+            Hazard::Flood => 6.0,
+            Hazard::Drought => 15.0,
+            Hazard::Eruption => params.eruption_radius,
+            Hazard::Earthquake => 10.0,
+        }
+    }
+
+    /// Share of fertility an event takes from a tile at full risk.
+    fn fertility_loss(&self) -> f64 {
+        match self {
+            Hazard::Flood => 0.5,
+            Hazard::Drought => 0.6,
+            Hazard::Eruption => 0.9,
+            Hazard::Earthquake => 0.2,
+        }
+    }
+
+    /// Share of game an event kills on a tile at full risk.
+    fn fauna_loss(&self, species: Species) -> f64 {
+        match (self, species) {
+            (Hazard::Eruption, _) => 0.9,
+            (Hazard::Drought, Species::Grazers | Species::ForestGame | Species::FishRuns) => 0.5,
+            (Hazard::Flood, Species::Grazers | Species::ForestGame) => 0.2,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Display for Hazard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Hazard::Flood => write!(f, "flood"),
+            Hazard::Drought => write!(f, "drought"),
+            Hazard::Eruption => write!(f, "eruption"),
+            Hazard::Earthquake => write!(f, "earthquake"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HazardEvent {
+    pub year: u32,
+    pub hazard: Hazard,
+    /// Tile at the center of the event
+    pub tile: usize,
+    /// Number of tiles hit
+    pub area: usize,
+}
+
+/// Sets the risk of every hazard on every tile. Flood plains are low, wet
+/// land, as rivers are not generated yet; droughts hit the band just wetter
+/// than deserts; eruptions threaten the surroundings of volcanoes; fault
+/// lines follow the zero crossings of a separate noise field.
+pub fn assess(tiles: &mut [Tile], viewport: &Viewport, params: &WorldParameters, seed: u64) {
+    let hazards = match &params.hazards {
+        Some(hazards) => hazards,
+        None => return,
+    };
+    let faults = noise::Fbm::<noise::SuperSimplex>::new(seed as u32);
+    let vents = tiles.iter()
+        .filter_map(|tile| tile.volcano.map(|state| (viewport.sample(tile.x, tile.y), state)))
+        .collect::<Vec<((f64, f64), Volcano)>>();
+
+    for tile in tiles.iter_mut() {
+        let (sx, sy) = viewport.sample(tile.x, tile.y);
+        let land = !tile.is_sea() && tile.ice.is_none();
+        let wetness = ((tile.rainfall + 1.0) / 2.0).clamp(0.0, 1.0);
+
+        let flood = if land {
+            let lowness = (1.0 - (tile.altitude - params.sea_level) / hazards.flood_height).clamp(0.0, 1.0);
+            if tile.biome == Biome::Wetland { wetness.max(lowness * wetness) } else { lowness * wetness }
+        } else { 0.0 };
+
+        let drought = if !land { 0.0 }
+            else if tile.rainfall < params.desert_r { DESERT_RISK }
+            else { (1.0 - (tile.rainfall - params.desert_r) / hazards.drought_band).clamp(0.0, 1.0) };
+
+        let eruption = vents.iter().map(|((vx, vy), state)| {
+            let distance = ((sx - vx).powi(2) + (sy - vy).powi(2)).sqrt();
+            let weight = if *state == Volcano::Active { 1.0 } else { DORMANT_RISK };
+            weight * (1.0 - distance / hazards.eruption_radius).max(0.0)
+        }).fold(0.0, f64::max);
+
+        let fault = faults.get([sx / hazards.fault_scale, sy / hazards.fault_scale]).abs();
+        let earthquake = (1.0 - fault / hazards.fault_width).max(0.0);
+
+        tile.risk = [flood, drought, eruption, earthquake];
+    }
+}
+
+impl Tile {
+    pub fn risk(&self, hazard: Hazard) -> f64 {
+        self.risk[hazard as usize]
+    }
+}
+
+impl World {
+    /// Runs the world forward by `years`: each year lost fertility and game
+    /// recover, then hazards fire at random according to tile risk. The climate
+    /// then follows its timeline over the same years. The same seed always gives
+    /// the same events in the same year. Returns the events fired.
+    pub fn advance(&mut self, years: u32) -> Vec<HazardEvent> {
+        let start = self.year;
+        let mut events = Vec::new();
+        for _ in 0..years {
+            self.year += 1;
+            self.recover();
+            if let Some(hazards) = self.params.hazards.clone() {
+                events.extend(self.roll_events(&hazards));
+            }
+        }
+        self.advance_climate(start);
+        events.iter().for_each(|event| {
+            let tile = &self.tiles[event.tile];
+            println!("[MapGen] Year {}: {} at ({}, {}) hit {} tiles.", event.year, event.hazard, tile.x, tile.y, event.area);
+        });
+        self.events.extend(events.iter().cloned());
+        events
+    }
+
+    fn recover(&mut self) {
+        let recovery = self.params.hazards.as_ref().map_or(1.0, |hazards| 1.0 / hazards.recovery_years.max(1.0));
+        self.tiles.iter_mut().for_each(|tile| {
+            tile.damage = (tile.damage - recovery).max(0.0);
+            tile.fauna.regrow(1.0);
+        });
+    }
+
+    fn roll_events(&mut self, hazards: &HazardParameters) -> Vec<HazardEvent> {
+        let mut rng = StdRng::seed_from_u64(self.seed.derive(&format!("hazards-{}", self.year)));
+        let rolls = (self.size / ROLL_AREA).max(1);
+        let mut events = Vec::new();
+
+        for hazard in Hazard::ALL {
+            for _ in 0..rolls {
+                let center = rng.gen_range(0..self.size);
+                let chance = self.tiles[center].risk(hazard) * hazards.rates[hazard as usize];
+                if rng.gen::<f64>() >= chance { continue; }
+                let area = self.strike(hazard, center, hazard.reach(hazards) * self.viewport.scale);
+                events.push(HazardEvent { year: self.year, hazard, tile: center, area });
+            }
+        }
+        events
+    }
+
+    /// Damages every at-risk tile within `radius` tiles of `center`.
+    fn strike(&mut self, hazard: Hazard, center: usize, radius: f64) -> usize {
+        let (cx, cy) = (self.tiles[center].x, self.tiles[center].y);
+        let r = radius.ceil() as i64;
+        let mut area = 0;
+        for y in (cy as i64 - r).max(0)..=(cy as i64 + r).min(self.height as i64 - 1) {
+            for x in (cx as i64 - r).max(0)..=(cx as i64 + r).min(self.width as i64 - 1) {
+                let distance = ((x as f64 - cx).powi(2) + (y as f64 - cy).powi(2)).sqrt();
+                let tile = &mut self.tiles[x as usize + y as usize * self.width];
+                let strength = tile.risk(hazard) * (1.0 - distance / radius.max(1.0));
+                if strength <= 0.0 { continue; }
+                tile.damage = (tile.damage + hazard.fertility_loss() * strength).min(1.0);
+                Species::ALL.iter().for_each(|species| {
+                    tile.fauna.density[*species as usize] *= 1.0 - hazard.fauna_loss(*species) * strength;
+                });
+                area += 1;
+            }
+        }
+        area
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use clap::Parser;
+    use crate::{
+        map::{guide::AltitudeGuides, presets::load_preset, world::WorldBuilder},
+        utils::cli::Args
+    };
+
+    #[test]
+    fn the_same_seed_gives_the_same_events() {
+        let args = Args::parse_from(["civ-sim", "-x", "80", "-y", "60", "-s", "hazards"]);
+        let params = load_preset("earthlike").unwrap();
+        let guides = Arc::new(AltitudeGuides::load(&args, &params).unwrap());
+        let mut worlds = (0..2).map(|_| WorldBuilder::new(&args, &params, &guides).unwrap().build()).collect::<Vec<_>>();
+
+        let [first, second] = [0, 1].map(|i| (0..4).flat_map(|_| worlds[i].advance(25)).collect::<Vec<_>>());
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+        assert_eq!(worlds[0].events, worlds[1].events);
+    }
+}
@@ -0,0 +1,58 @@
+use nannou::image::{self, imageops::FilterType, GenericImageView};
+
+/// A grayscale image used as an altitude source, stretched over the world
+/// dimensions. Black maps to -1.0 and white to 1.0, so mid-gray sits at the
+/// default sea level. Images larger than the world are shrunk to it; smaller
+/// ones are kept as they are and interpolated, so huge worlds do not hold a
+/// full-size copy.
+pub struct Heightmap {
+    width: usize,
+    height: usize,
+    /// Heightmap pixels per tile along each axis
+    scale: (f64, f64),
+    values: Vec<f64>,
+    blend: f64,
+}
+
+impl Heightmap {
+    pub fn load(path: &str, width: usize, height: usize, blend: f64) -> Result<Heightmap, String> {
+        println!("[MapGen] Loading heightmap from {}", path);
+        let img = image::open(path)
+            .map_err(|e| format!("[MapGen] Failed to load heightmap '{}': {}", path, e))?;
+        let (w, h) = (width.min(img.width() as usize).max(1), height.min(img.height() as usize).max(1));
+        let img = img
+            .resize_exact(w as u32, h as u32, FilterType::Triangle)
+            .to_luma16();
+
+        let values = img.pixels()
+            .map(|pixel| (pixel[0] as f64 / u16::MAX as f64) * 2.0 - 1.0)
+            .collect::<Vec<f64>>();
+
+        Ok(Heightmap {
+            width: w,
+            height: h,
+            scale: (w as f64 / width as f64, h as f64 / height as f64),
+            values,
+            blend: blend.clamp(0.0, 1.0),
+        })
+    }
+
+    /// Bilinear sample at tile coordinates, so zoomed regions read between
+    /// heightmap pixels smoothly.
+    pub fn sample(&self, x: f64, y: f64) -> f64 {
+        let fx = ((x + 0.5) * self.scale.0 - 0.5).clamp(0.0, (self.width - 1) as f64);
+        let fy = ((y + 0.5) * self.scale.1 - 0.5).clamp(0.0, (self.height - 1) as f64);
+        let (x0, y0) = (fx as usize, fy as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (fx - x0 as f64, fy - y0 as f64);
+        let top = self.values[x0 + self.width * y0] * (1.0 - tx) + self.values[x1 + self.width * y0] * tx;
+        let bottom = self.values[x0 + self.width * y1] * (1.0 - tx) + self.values[x1 + self.width * y1] * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    /// Mixes the heightmap value at (x, y) into the noise altitude.
+    /// A blend of 1.0 replaces the noise entirely; 0.0 ignores the heightmap.
+    pub fn apply(&self, altitude: f64, x: f64, y: f64) -> f64 {
+        altitude * (1.0 - self.blend) + self.sample(x, y) * self.blend
+    }
+}
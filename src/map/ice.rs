@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use crate::{
+    map::{tile::Tile, world::WorldParameters},
+    utils::helpers::neighbors8
+};
+
+/// Accumulation past the thresholds that builds the thickest ice.
+const ICE_DEPTH: f64 = 0.1;
+/// Thickness every glacier loses per tile it flows, even in the cold.
+const FLOW_LOSS: f64 = 0.05;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct IceParameters {
+    /// Land colder than this keeps its snow all year and builds ice
+    #[serde(default = "default_accumulation_t")]
+    pub accumulation_t: f64,
+    /// Ice only builds where rainfall is at least this
+    #[serde(default = "default_min_rainfall")]
+    pub min_rainfall: f64,
+    /// Snowline altitude at the equator
+    #[serde(default = "default_snowline_equator")]
+    pub snowline_equator: f64,
+    /// Snowline altitude at the poles
+    #[serde(default = "default_snowline_pole")]
+    pub snowline_pole: f64,
+    /// Number of tiles glaciers may flow downhill
+    #[serde(default = "default_flow_passes")]
+    pub flow_passes: usize,
+    /// Thickness lost per tile flowed, per unit of temperature above `accumulation_t`
+    #[serde(default = "default_melt")]
+    pub melt: f64,
+    /// Sea colder than this freezes over in winter; no sea ice if absent
+    #[serde(default)]
+    pub sea_ice_t: Option<f64>,
+}
+
+fn default_accumulation_t() -> f64 { -0.95 }
+fn default_min_rainfall() -> f64 { -0.6 }
+fn default_snowline_equator() -> f64 { 0.9 }
+fn default_snowline_pole() -> f64 { 0.5 }
+fn default_flow_passes() -> usize { 8 }
+fn default_melt() -> f64 { 1.0 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IceKind {
+    /// Permanent ice over cold lowland
+    Sheet,
+    /// Permanent snow above the snowline
+    Snowcap,
+    /// Ice flowing downhill out of a sheet or snowcap
+    Glacier,
+    /// Seasonal ice over cold sea
+    SeaIce,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Ice {
+    pub kind: IceKind,
+    /// From 0.0 to 1.0
+    pub thickness: f64,
+}
+
+impl IceParameters {
+    /// Snowline altitude at a signed latitude.
+    pub fn snowline(&self, latitude: f64) -> f64 {
+        self.snowline_equator + (self.snowline_pole - self.snowline_equator) * latitude.abs()
+    }
+
+    /// Ice built on a tile by its own climate, before any flow.
+    fn accumulation(&self, tile: &Tile) -> Option<Ice> {
+        if tile.rainfall < self.min_rainfall { return None; }
+        let cold = self.accumulation_t - tile.temperature;
+        let high = tile.altitude - self.snowline(tile.latitude);
+        if cold <= 0.0 && high <= 0.0 { return None; }
+        Some(Ice {
+            kind: if high > 0.0 { IceKind::Snowcap } else { IceKind::Sheet },
+            thickness: (cold.max(high) / ICE_DEPTH).min(1.0),
+        })
+    }
+}
+
+/// Places permanent ice on cold or high land, lets it flow downhill into
+/// glaciers that melt as they reach warmer ground, then freezes cold sea.
+/// Returns the number of iced tiles.
+pub fn generate_ice(tiles: &mut [Tile], width: usize, params: &WorldParameters) -> usize {
+    let ice = match &params.ice {
+        Some(ice) => ice,
+        None => return 0,
+    };
+    let size = tiles.len();
+    let mut cover = tiles.iter()
+        .map(|tile| if tile.is_sea() { None } else { ice.accumulation(tile) })
+        .collect::<Vec<Option<Ice>>>();
+
+    for _ in 0..ice.flow_passes {
+        let next = (0..size).map(|i| {
+            let tile = &tiles[i];
+            if tile.is_sea() || cover[i].is_some_and(|ice| ice.kind != IceKind::Glacier) { return cover[i]; }
+            let loss = FLOW_LOSS + ice.melt * (tile.temperature - ice.accumulation_t).max(0.0);
+            let inflow = neighbors8(i, width, size).iter()
+                .filter(|n| tiles[**n].altitude > tile.altitude)
+                .filter_map(|n| cover[*n].map(|ice| ice.thickness - loss))
+                .fold(0.0, f64::max);
+            (inflow > 0.0).then_some(Ice { kind: IceKind::Glacier, thickness: inflow })
+        }).collect::<Vec<Option<Ice>>>();
+        if next.iter().zip(&cover).all(|(a, b)| a.map(|a| a.thickness) == b.map(|b| b.thickness)) { break; }
+        cover = next;
+    }
+
+    if let Some(sea_ice_t) = ice.sea_ice_t {
+        tiles.iter().enumerate().filter(|(_, tile)| tile.is_sea()).for_each(|(i, tile)| {
+            let cold = sea_ice_t - tile.temperature;
+            if cold > 0.0 {
+                cover[i] = Some(Ice { kind: IceKind::SeaIce, thickness: (cold / ICE_DEPTH).min(1.0) });
+            }
+        });
+    }
+
+    tiles.iter_mut().zip(cover).for_each(|(tile, ice)| tile.ice = ice);
+    tiles.iter().filter(|tile| tile.ice.is_some()).count()
+}
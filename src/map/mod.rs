@@ -1,2 +1,18 @@
+pub mod chunk;
+pub mod cleanup;
+pub mod climate;
+pub mod fauna;
+pub mod features;
+pub mod guide;
+pub mod hazards;
+pub mod heightmap;
+pub mod ice;
+pub mod noise_stack;
+pub mod normalize;
+pub mod presets;
+pub mod stats;
 pub mod tile;
+pub mod validation;
+pub mod volcanism;
 pub mod world;
+pub mod yields;
@@ -0,0 +1,97 @@
+use noise::{Fbm, MultiFractal, SuperSimplex};
+use serde::{Deserialize, Serialize};
+
+/// Fractal noise settings for one noise layer. The defaults are those of
+/// `Fbm`, so parameter files without a stack keep generating the same worlds.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct NoiseStack {
+    /// Number of octaves summed, each finer than the one before
+    #[serde(default = "default_octaves")]
+    pub octaves: usize,
+    /// Frequency of the coarsest octave, on top of the layer's `*_scale`
+    #[serde(default = "default_frequency")]
+    pub frequency: f64,
+    /// Frequency multiplier from one octave to the next
+    #[serde(default = "default_lacunarity")]
+    pub lacunarity: f64,
+    /// Amplitude multiplier from one octave to the next
+    #[serde(default = "default_persistence")]
+    pub persistence: f64,
+}
+
+fn default_octaves() -> usize { Fbm::<SuperSimplex>::DEFAULT_OCTAVE_COUNT }
+fn default_frequency() -> f64 { Fbm::<SuperSimplex>::DEFAULT_FREQUENCY }
+fn default_lacunarity() -> f64 { Fbm::<SuperSimplex>::DEFAULT_LACUNARITY }
+fn default_persistence() -> f64 { Fbm::<SuperSimplex>::DEFAULT_PERSISTENCE }
+
+impl Default for NoiseStack {
+    fn default() -> Self {
+        NoiseStack {
+            octaves: default_octaves(),
+            frequency: default_frequency(),
+            lacunarity: default_lacunarity(),
+            persistence: default_persistence(),
+        }
+    }
+}
+
+impl NoiseStack {
+    pub fn build(&self, seed: u32) -> Fbm<SuperSimplex> {
+        Fbm::<SuperSimplex>::new(seed)
+            .set_octaves(self.octaves)
+            .set_frequency(self.frequency)
+            .set_lacunarity(self.lacunarity)
+            .set_persistence(self.persistence)
+    }
+}
+
+/// One stack per noise layer, in the order of `seed::LAYERS`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct NoiseStacks {
+    #[serde(default)]
+    pub temperature: NoiseStack,
+    #[serde(default)]
+    pub altitude: NoiseStack,
+    #[serde(default)]
+    pub rainfall: NoiseStack,
+    #[serde(default)]
+    pub detail: NoiseStack,
+}
+
+impl NoiseStacks {
+    pub fn layers(&self) -> [(&'static str, NoiseStack); 4] {
+        [
+            ("temperature", self.temperature),
+            ("altitude", self.altitude),
+            ("rainfall", self.rainfall),
+            ("detail", self.detail),
+        ]
+    }
+
+    /// Builds every layer's noise from its seed.
+    pub fn build(&self, seeds: [u32; 4]) -> [Fbm<SuperSimplex>; 4] {
+        let layers = self.layers();
+        std::array::from_fn(|i| layers[i].1.build(seeds[i]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use noise::NoiseFn;
+    use super::*;
+
+    #[test]
+    fn default_stack_matches_plain_fbm() {
+        let (stacked, plain) = (NoiseStack::default().build(7), Fbm::<SuperSimplex>::new(7));
+        for point in [[0.0, 0.0], [0.3, 1.7], [-12.5, 4.25]] {
+            assert_eq!(stacked.get(point), plain.get(point));
+        }
+    }
+
+    #[test]
+    fn stacks_change_the_noise() {
+        let stack = NoiseStack { octaves: 3, ..NoiseStack::default() };
+        let (stacked, plain) = (stack.build(7), Fbm::<SuperSimplex>::new(7));
+        assert_ne!(stacked.get([0.3, 1.7]), plain.get([0.3, 1.7]));
+    }
+}
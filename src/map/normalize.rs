@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use crate::{
+    map::tile::{Field, Tile},
+    utils::helpers::quantile
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum Normalization {
+    /// Stretch the observed range onto [-1.0, 1.0]
+    MinMax,
+    /// Stretch the range between two quantiles onto [-1.0, 1.0], clamping outliers
+    PercentileClamp { low: f64, high: f64 },
+    /// Flatten the value distribution so every output band holds as many tiles
+    Equalize {
+        #[serde(default = "default_bins")]
+        bins: usize
+    },
+    /// Piecewise-linear mapping given as [input, output] points
+    Curve { points: Vec<[f64; 2]> },
+}
+
+fn default_bins() -> usize { 256 }
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct NormalizationParameters {
+    #[serde(default)]
+    pub altitude: Option<Normalization>,
+    #[serde(default)]
+    pub temperature: Option<Normalization>,
+    #[serde(default)]
+    pub rainfall: Option<Normalization>,
+}
+
+impl NormalizationParameters {
+    pub fn fields(&self) -> Vec<(Field, &Normalization)> {
+        [
+            (Field::Altitude, &self.altitude),
+            (Field::Temperature, &self.temperature),
+            (Field::Rainfall, &self.rainfall),
+        ]
+            .into_iter()
+            .filter_map(|(field, strategy)| strategy.as_ref().map(|strategy| (field, strategy)))
+            .collect()
+    }
+}
+
+/// A normalization fitted to one generated field. Keeping the fitted transform
+/// rather than the strategy lets other tiles of the same world be mapped identically.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FieldTransform {
+    Linear { min: f64, max: f64 },
+    Lookup { points: Vec<[f64; 2]> },
+}
+
+impl FieldTransform {
+    pub fn fit(strategy: &Normalization, values: &[f64]) -> FieldTransform {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        match strategy {
+            Normalization::MinMax => FieldTransform::Linear {
+                min: sorted[0],
+                max: sorted[sorted.len() - 1]
+            },
+            Normalization::PercentileClamp { low, high } => FieldTransform::Linear {
+                min: quantile(&sorted, *low),
+                max: quantile(&sorted, *high)
+            },
+            Normalization::Equalize { bins } => {
+                let bins = (*bins).max(1);
+                let mut points = Vec::<[f64; 2]>::with_capacity(bins + 1);
+                for i in 0..=bins {
+                    let input = quantile(&sorted, i as f64 / bins as f64);
+                    let output = -1.0 + 2.0 * i as f64 / bins as f64;
+                    match points.last_mut() {
+                        Some(last) if last[0] >= input => last[1] = (last[1] + output) / 2.0,
+                        _ => points.push([input, output])
+                    }
+                }
+                FieldTransform::Lookup { points }
+            },
+            Normalization::Curve { points } => {
+                let mut points = points.clone();
+                points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+                FieldTransform::Lookup { points }
+            }
+        }
+    }
+
+    pub fn apply(&self, value: f64) -> f64 {
+        match self {
+            FieldTransform::Linear { min, max } => {
+                if max <= min { return 0.0; }
+                (((value - min) / (max - min)) * 2.0 - 1.0).clamp(-1.0, 1.0)
+            },
+            FieldTransform::Lookup { points } => {
+                if points.is_empty() { return value; }
+                let i = points.partition_point(|point| point[0] < value);
+                if i == 0 { return points[0][1]; }
+                if i == points.len() { return points[i - 1][1]; }
+                let ([x0, y0], [x1, y1]) = (points[i - 1], points[i]);
+                y0 + (y1 - y0) * (value - x0) / (x1 - x0)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NormalizationRecord {
+    pub field: Field,
+    pub strategy: Normalization,
+    pub transform: FieldTransform,
+    /// Range of the field before normalization
+    pub source_range: (f64, f64),
+}
+
+impl NormalizationRecord {
+    pub fn fit(field: Field, strategy: &Normalization, tiles: &[Tile]) -> NormalizationRecord {
+        let values = tiles.iter().map(|tile| tile.field(field)).collect::<Vec<f64>>();
+        let source_range = values.iter().fold((f64::MAX, f64::MIN), |(min, max), value| {
+            (min.min(*value), max.max(*value))
+        });
+
+        NormalizationRecord {
+            field,
+            strategy: strategy.clone(),
+            transform: FieldTransform::fit(strategy, &values),
+            source_range,
+        }
+    }
+
+    pub fn apply(&self, tile: &mut Tile) {
+        let value = self.transform.apply(tile.field(self.field));
+        *tile.field_mut(self.field) = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A skewed sample, clustered near zero like raw FBM output.
+    fn sample() -> Vec<f64> {
+        (0..1000).map(|i| (i as f64 / 999.0 * 2.0 - 1.0).powi(3) * 0.4).collect()
+    }
+
+    fn close(a: f64, b: f64) -> bool { (a - b).abs() < 1e-9 }
+
+    #[test]
+    fn min_max_stretches_the_observed_range() {
+        let values = sample();
+        let transform = FieldTransform::fit(&Normalization::MinMax, &values);
+        assert!(close(transform.apply(-0.4), -1.0));
+        assert!(close(transform.apply(0.4), 1.0));
+        assert!(close(transform.apply(0.0), 0.0));
+        assert!(close(transform.apply(0.2), 0.5));
+    }
+
+    #[test]
+    fn min_max_of_a_constant_field_is_zero() {
+        let transform = FieldTransform::fit(&Normalization::MinMax, &[0.3; 10]);
+        assert!(close(transform.apply(0.3), 0.0));
+    }
+
+    #[test]
+    fn percentile_clamp_clips_outliers() {
+        let values = sample();
+        let transform = FieldTransform::fit(&Normalization::PercentileClamp { low: 0.1, high: 0.9 }, &values);
+        let mut sorted = values.clone();
+        sorted.sort_by(f64::total_cmp);
+        assert!(close(transform.apply(quantile(&sorted, 0.1)), -1.0));
+        assert!(close(transform.apply(quantile(&sorted, 0.9)), 1.0));
+        assert!(close(transform.apply(-0.4), -1.0));
+        assert!(close(transform.apply(0.4), 1.0));
+    }
+
+    #[test]
+    fn equalize_spreads_values_evenly() {
+        let values = sample();
+        let transform = FieldTransform::fit(&Normalization::Equalize { bins: 64 }, &values);
+        let mapped = values.iter().map(|value| transform.apply(*value)).collect::<Vec<f64>>();
+
+        let mut counts = [0; 4];
+        mapped.iter().for_each(|value| counts[(((value + 1.0) / 2.0 * 4.0) as usize).min(3)] += 1);
+        counts.iter().for_each(|count| assert!((*count - 250_i32).abs() <= 10, "{:?}", counts));
+        assert!(mapped.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn curve_interpolates_between_sorted_points() {
+        let strategy = Normalization::Curve { points: vec![[1.0, 1.0], [-1.0, -1.0], [0.0, 0.5]] };
+        let transform = FieldTransform::fit(&strategy, &sample());
+        assert!(close(transform.apply(0.0), 0.5));
+        assert!(close(transform.apply(-0.5), -0.25));
+        assert!(close(transform.apply(0.5), 0.75));
+        assert!(close(transform.apply(2.0), 1.0));
+        assert!(close(transform.apply(-2.0), -1.0));
+    }
+}
@@ -0,0 +1,57 @@
+use serde_json::Value;
+use crate::{
+    map::world::WorldParameters,
+    utils::helpers::parse_parameters
+};
+
+/// Presets bundled into the binary. Any preset, and the parameters file itself,
+/// may name a base preset under `inherits` and override only what differs.
+pub const PRESETS: [(&str, &str); 5] = [
+    ("earthlike", include_str!("../presets/earthlike.json")),
+    ("pangaea", include_str!("../presets/pangaea.json")),
+    ("archipelago", include_str!("../presets/archipelago.json")),
+    ("ice_age", include_str!("../presets/ice_age.json")),
+    ("desert", include_str!("../presets/desert.json")),
+];
+
+pub fn preset_names() -> Vec<&'static str> {
+    PRESETS.iter().map(|(name, _)| *name).collect()
+}
+
+/// Recursively merges `overrides` into `base`, replacing anything that is not an object.
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        },
+        (base, overrides) => *base = overrides
+    }
+}
+
+/// Follows the `inherits` chain of a parameters object, merging each level over its base.
+pub fn resolve(mut value: Value, chain: &mut Vec<String>) -> Result<Value, String> {
+    let base = match value.as_object_mut().and_then(|object| object.remove("inherits")) {
+        None => return Ok(value),
+        Some(Value::String(name)) => name,
+        Some(other) => return Err(format!("'inherits' should be a preset name, found {}.", other)),
+    };
+    if chain.contains(&base) {
+        return Err(format!("Preset inheritance loops through '{}'.", base));
+    }
+    chain.push(base.clone());
+
+    let (_, data) = PRESETS.iter()
+        .find(|(name, _)| *name == base)
+        .ok_or(format!("Unknown preset '{}'; expected one of {}.", base, preset_names().join(", ")))?;
+    let parent = serde_json::from_str::<Value>(data)
+        .map_err(|e| format!("Preset '{}' is not valid JSON: {}", base, e))?;
+    let mut merged = resolve(parent, chain)?;
+    merge(&mut merged, value);
+    Ok(merged)
+}
+
+pub fn load_preset(name: &str) -> Result<WorldParameters, String> {
+    parse_parameters(serde_json::json!({ "inherits": name }), &format!("preset '{}'", name))
+}
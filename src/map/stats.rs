@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use crate::{
+    map::{
+        fauna::Species,
+        tile::{Biome, Field},
+        world::World,
+        yields::Yields
+    },
+    utils::helpers::{adjacent, quantile, regions}
+};
+
+const HISTOGRAM_BINS: usize = 64;
+const PERCENTILES: [f64; 7] = [0.01, 0.05, 0.25, 0.5, 0.75, 0.95, 0.99];
+const LARGEST_LANDMASSES: usize = 10;
+
+#[derive(Serialize)]
+pub struct FieldStats {
+    pub field: Field,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Keyed by percentile, e.g. "p05" or "p50"
+    pub percentiles: BTreeMap<String, f64>,
+    /// Tile counts over `HISTOGRAM_BINS` equal bins between min and max
+    pub histogram: Vec<usize>,
+}
+
+#[derive(Serialize)]
+pub struct FaunaStats {
+    /// Mean population over all tiles, from 0.0 to 1.0
+    pub mean_density: f64,
+    /// Share of tiles with any population
+    pub range: f64,
+    pub regrowth: f64,
+}
+
+#[derive(Serialize)]
+pub struct WorldStats {
+    pub seed: String,
+    pub width: usize,
+    pub height: usize,
+    pub fields: Vec<FieldStats>,
+    /// Share of tiles per biome, from 0.0 to 1.0
+    pub biomes: BTreeMap<Biome, f64>,
+    pub land_ratio: f64,
+    /// Number of land/sea tile edges
+    pub coastline_length: usize,
+    pub landmass_count: usize,
+    /// Tile counts of the largest landmasses, largest first
+    pub largest_landmasses: Vec<usize>,
+    /// Mean yields of land tiles, blended across ecotones
+    pub land_yields: Yields,
+    pub fauna: BTreeMap<Species, FaunaStats>,
+}
+
+impl FieldStats {
+    fn new(field: Field, world: &World) -> FieldStats {
+        let mut values = world.tiles.iter().map(|tile| tile.field(field)).collect::<Vec<f64>>();
+        values.sort_by(f64::total_cmp);
+        let (min, max) = (values[0], values[values.len() - 1]);
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+
+        let mut histogram = vec![0; HISTOGRAM_BINS];
+        let width = (max - min).max(f64::EPSILON);
+        values.iter().for_each(|value| {
+            let bin = (((value - min) / width) * HISTOGRAM_BINS as f64) as usize;
+            histogram[bin.min(HISTOGRAM_BINS - 1)] += 1;
+        });
+
+        FieldStats {
+            field,
+            min,
+            max,
+            mean,
+            percentiles: PERCENTILES.iter()
+                .map(|p| (format!("p{:02}", (p * 100.0).round() as usize), quantile(&values, *p)))
+                .collect(),
+            histogram,
+        }
+    }
+}
+
+impl WorldStats {
+    pub fn new(world: &World) -> WorldStats {
+        let size = world.size as f64;
+
+        let mut counts = BTreeMap::<Biome, usize>::new();
+        world.tiles.iter().for_each(|tile| *counts.entry(tile.biome).or_insert(0) += 1);
+        let biomes = counts.into_iter()
+            .map(|(biome, count)| (biome, count as f64 / size))
+            .collect::<BTreeMap<Biome, f64>>();
+
+        let coastline_length = world.tiles.iter()
+            .filter(|tile| !tile.is_sea())
+            .map(|tile| adjacent(tile.id, world.width, world.size).iter()
+                .filter(|neighbor| world.tiles[**neighbor].is_sea())
+                .count())
+            .sum();
+
+        let mut landmasses = regions(world.width, world.size, |i| !world.tiles[i].is_sea())
+            .iter()
+            .map(|region| region.len())
+            .collect::<Vec<usize>>();
+        landmasses.sort_by(|a, b| b.cmp(a));
+        let land_tiles = landmasses.iter().sum::<usize>();
+        let land_yields = world.tiles.iter()
+            .filter(|tile| !tile.is_sea())
+            .fold(Yields::default(), |total, tile| total.add(&tile.yields()))
+            .scale(1.0 / land_tiles.max(1) as f64);
+
+        WorldStats {
+            seed: world.seed.to_string(),
+            width: world.width,
+            height: world.height,
+            fields: Field::ALL.iter().map(|field| FieldStats::new(*field, world)).collect(),
+            biomes,
+            land_ratio: land_tiles as f64 / size,
+            coastline_length,
+            landmass_count: landmasses.len(),
+            largest_landmasses: landmasses.into_iter().take(LARGEST_LANDMASSES).collect(),
+            land_yields,
+            fauna: Species::ALL.iter().map(|species| (*species, FaunaStats {
+                mean_density: world.tiles.iter().map(|tile| tile.fauna.density(*species)).sum::<f64>() / size,
+                range: world.tiles.iter().filter(|tile| tile.fauna.density(*species) > 0.0).count() as f64 / size,
+                regrowth: species.regrowth(),
+            })).collect(),
+        }
+    }
+}
@@ -1,14 +1,28 @@
+use std::fmt::{Display, Formatter, Result};
 use noise::NoiseFn;
-use crate::map::world::WorldParameters;
+use serde::{Deserialize, Serialize};
+use crate::map::{
+    world::{WorldParameters, Viewport},
+    guide::AltitudeGuides,
+    fauna::Fauna,
+    ice::Ice,
+    volcanism::Volcano
+};
 
-#[derive(Debug, Clone)]
+/// Probes per field and direction when looking for the nearest biome threshold.
+const ECOTONE_STEPS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Biome {
     Boreal,
     Coast,
     Desert,
     Frozen,
+    #[serde(alias = "hills")]
     Hill,
     Mountain,
+    #[serde(alias = "peaks")]
     Peak,
     Plains,
     Sea,
@@ -19,15 +33,131 @@ pub enum Biome {
     Debug,
 }
 
+impl Display for Biome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let name = match self {
+            Biome::Boreal => "boreal",
+            Biome::Coast => "coast",
+            Biome::Desert => "desert",
+            Biome::Frozen => "frozen",
+            Biome::Hill => "hill",
+            Biome::Mountain => "mountain",
+            Biome::Peak => "peak",
+            Biome::Plains => "plains",
+            Biome::Sea => "sea",
+            Biome::Rainforest => "rainforest",
+            Biome::Temperate => "temperate",
+            Biome::Tundra => "tundra",
+            Biome::Wetland => "wetland",
+            Biome::Debug => "debug",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Biome {
+    pub fn from_climate(
+        altitude: f64,
+        temperature: f64,
+        rainfall: f64,
+        params: &WorldParameters,
+    ) -> Biome {
+        if      altitude    >= params.peak_h                                          { Biome::Peak }
+        else if altitude    >= params.mountain_h                                      { Biome::Mountain }
+        else if altitude    >= params.hills_h                                         { Biome::Hill }
+        else if altitude    <= params.sea_level                                       { Biome::Sea }
+        else if temperature <= params.frozen_t                                        { Biome::Frozen }
+        else if temperature <= params.tundra_t                                        { Biome::Tundra }
+        else if temperature <= params.boreal_t && rainfall >= params.boreal_r         { Biome::Boreal }
+        else if rainfall    >= params.wetlands_r                                      { Biome::Wetland }
+        else if temperature >= params.rainforest_t && rainfall >= params.rainforest_r { Biome::Rainforest }
+        else if temperature <= params.temperate_t && rainfall >= params.temperate_r   { Biome::Temperate }
+        else if rainfall    <= params.desert_r                                        { Biome::Desert }
+        else if temperature + rainfall <= params.plains_cutoff                        { Biome::Plains }
+        else                                                                          { Biome::Debug }
+    }
+
+    /// The biome across the nearest threshold within `params.ecotone_width` of
+    /// the given climate, with its weight: close to 0.5 right at the threshold,
+    /// fading to 0.0 at the edge of the band. Land and sea never blend.
+    pub fn ecotone(
+        altitude: f64,
+        temperature: f64,
+        rainfall: f64,
+        params: &WorldParameters,
+    ) -> (Biome, f64) {
+        let biome = Biome::from_climate(altitude, temperature, rainfall, params);
+        let width = params.ecotone_width;
+        if width <= 0.0 || biome == Biome::Sea { return (biome, 0.0); }
+
+        let mut nearest = (biome, 0.0);
+        for field in Field::ALL {
+            for sign in [-1.0, 1.0] {
+                let across = (1..=ECOTONE_STEPS).find_map(|step| {
+                    let mut climate = [altitude, temperature, rainfall];
+                    climate[field as usize] += sign * width * step as f64 / ECOTONE_STEPS as f64;
+                    let other = Biome::from_climate(climate[0], climate[1], climate[2], params);
+                    (other != biome).then_some((other, step))
+                });
+                if let Some((other, step)) = across {
+                    let distance = (step as f64 - 0.5) / ECOTONE_STEPS as f64;
+                    let blend = 0.5 * (1.0 - distance);
+                    if !matches!(other, Biome::Sea | Biome::Debug) && blend > nearest.1 {
+                        nearest = (other, blend);
+                    }
+                }
+            }
+        }
+        nearest
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Altitude,
+    Temperature,
+    Rainfall,
+}
+
+impl Field {
+    pub const ALL: [Field; 3] = [Field::Altitude, Field::Temperature, Field::Rainfall];
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Field::Altitude => write!(f, "altitude"),
+            Field::Temperature => write!(f, "temperature"),
+            Field::Rainfall => write!(f, "rainfall"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Tile {
     pub id: usize,
     pub x: f64,
     pub y: f64,
+    /// Signed distance to the equator; -1.0 at the top edge, 1.0 at the bottom
+    pub latitude: f64,
     pub altitude: f64,
     pub temperature: f64,
     pub rainfall: f64,
     pub biome: Biome,
+    /// Neighboring biome this tile transitions into
+    pub ecotone: Biome,
+    /// Weight of `ecotone`, from 0.0 (pure `biome`) to 0.5
+    pub blend: f64,
+    pub ice: Option<Ice>,
+    pub volcano: Option<Volcano>,
+    /// Multiplies food yields; 1.0 is ordinary soil
+    pub fertility: f64,
+    pub fauna: Fauna,
+    /// Hazard risk indexed like `Hazard::ALL`, from 0.0 to 1.0
+    pub risk: [f64; 4],
+    /// Share of fertility lost to recent hazards, recovering over time
+    pub damage: f64,
 }
 
 impl Tile {
@@ -35,21 +165,26 @@ impl Tile {
         id: usize,
         x: f64,
         y: f64,
-        equator: &f64,
+        viewport: &Viewport,
         noise: &[noise::Fbm<noise::SuperSimplex>; 4],
         params: &WorldParameters,
+        guides: &AltitudeGuides,
     ) -> Tile {
+        let (sx, sy) = viewport.sample(x, y);
+        let equator = viewport.equator;
+        let latitude = (sy - equator) / equator;
+
         let temperature: f64 = {
-            ((-(f64::abs(equator - y) / equator) * 8.0) * params.global_heat_scaling +
-            noise[0].get([x / params.temperature_scale, y / params.temperature_scale]) * 2.0)
+            ((-f64::abs(latitude) * 8.0) * params.global_heat_scaling +
+            noise[0].get([sx / params.temperature_scale, sy / params.temperature_scale]) * 2.0)
             / 10.0
         };
 
         let altitude: f64 = {
-            let w = x / params.altitude_scale;
-            let z = y / params.altitude_scale;
-            let x = x / (params.altitude_scale * 0.5);
-            let y = y / (params.altitude_scale * 0.5);
+            let w = sx / params.altitude_scale;
+            let z = sy / params.altitude_scale;
+            let x = sx / (params.altitude_scale * 0.5);
+            let y = sy / (params.altitude_scale * 0.5);
 
             let a = noise[1].get([w, z]);
             let b = noise[1].get([w + 0.003, z + 0.002]);
@@ -57,40 +192,58 @@ impl Tile {
             c / 2.0
         };
 
+        let altitude: f64 = guides.apply(altitude, sx, sy);
+
         let rainfall: f64 = {
-            let a: f64 = noise[2].get([x / params.rainfall_scale, y / params.rainfall_scale]);
-            let b: f64 = -(7.0 * (f64::abs(equator - y) / equator)).cos();
+            let a: f64 = noise[2].get([sx / params.rainfall_scale, sy / params.rainfall_scale]);
+            let b: f64 = -(7.0 * f64::abs(latitude)).cos();
             let c: f64 = f64::abs(temperature);
             (a + b + c) / 3.0
         };
 
-        let biome = {
-            if      altitude    >= params.peak_h                                          { Biome::Peak }
-            else if altitude    >= params.mountain_h                                      { Biome::Mountain }
-            else if altitude    >= params.hills_h                                         { Biome::Hill }
-            else if altitude    <= params.sea_level                                       { Biome::Sea }
-            else if temperature <= params.frozen_t                                        { Biome::Frozen }
-            else if temperature <= params.tundra_t                                        { Biome::Tundra }
-            else if temperature <= params.boreal_t && rainfall >= params.boreal_r         { Biome::Boreal }
-            else if rainfall    >= params.wetlands_r                                      { Biome::Wetland }
-            else if temperature >= params.rainforest_t && rainfall >= params.rainforest_r { Biome::Rainforest }
-            else if temperature <= params.temperate_t && rainfall >= params.temperate_r   { Biome::Temperate }
-            else if rainfall    <= params.desert_r                                        { Biome::Desert }
-            else if temperature + rainfall <= params.plains_cutoff                        { Biome::Plains }
-            else                                                                          { Biome::Debug }
-        };
-
-        Tile {
+        let mut tile = Tile {
             id,
             x,
             y,
+            latitude,
             altitude,
             temperature,
             rainfall,
-            biome
+            biome: Biome::Debug,
+            ecotone: Biome::Debug,
+            blend: 0.0,
+            ice: None,
+            volcano: None,
+            fertility: 1.0,
+            fauna: Fauna::default(),
+            risk: [0.0; 4],
+            damage: 0.0
+        };
+        tile.classify(params);
+        tile
+    }
+
+    pub fn field(&self, field: Field) -> f64 {
+        match field {
+            Field::Altitude => self.altitude,
+            Field::Temperature => self.temperature,
+            Field::Rainfall => self.rainfall,
         }
     }
 
+    pub fn field_mut(&mut self, field: Field) -> &mut f64 {
+        match field {
+            Field::Altitude => &mut self.altitude,
+            Field::Temperature => &mut self.temperature,
+            Field::Rainfall => &mut self.rainfall,
+        }
+    }
+
+    pub fn classify(&mut self, params: &WorldParameters) {
+        self.biome = Biome::from_climate(self.altitude, self.temperature, self.rainfall, params);
+        (self.ecotone, self.blend) = Biome::ecotone(self.altitude, self.temperature, self.rainfall, params);
+    }
+
     pub fn is_sea(&self) -> bool { matches!(self.biome, Biome::Sea) }
 }
 
@@ -104,7 +257,7 @@ impl Eq for Tile {}
 
 impl PartialOrd for Tile {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.id.cmp(&other.id))
+        Some(self.cmp(other))
     }
 }
 
@@ -113,3 +266,39 @@ impl Ord for Tile {
         self.id.cmp(&other.id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::map::presets::load_preset;
+    use super::*;
+
+    #[test]
+    fn blend_fades_across_the_band() {
+        let params = load_preset("earthlike").unwrap();
+        let at = |rainfall: f64| Biome::ecotone(0.2, 0.0, rainfall, &params);
+        assert_eq!(Biome::from_climate(0.2, 0.0, params.desert_r + 1e-6, &params), Biome::Plains);
+
+        let (ecotone, blend) = at(params.desert_r + 1e-6);
+        assert_eq!(ecotone, Biome::Desert);
+        assert!((blend - 0.5).abs() < 0.05, "blend {}", blend);
+        assert!(at(params.desert_r + params.ecotone_width / 2.0).1 < blend);
+        assert_eq!(at(params.desert_r + params.ecotone_width * 1.2), (Biome::Plains, 0.0));
+    }
+
+    #[test]
+    fn land_and_sea_never_blend() {
+        let params = load_preset("earthlike").unwrap();
+        assert_eq!(Biome::ecotone(params.sea_level + 1e-6, 0.0, 0.0, &params), (Biome::Plains, 0.0));
+        assert_eq!(Biome::ecotone(params.sea_level - 1e-6, 0.0, 0.0, &params), (Biome::Sea, 0.0));
+    }
+
+    #[test]
+    fn zero_width_turns_ecotones_off() {
+        let mut params = load_preset("earthlike").unwrap();
+        params.ecotone_width = 0.0;
+        for rainfall in [params.desert_r - 1e-6, params.desert_r + 1e-6] {
+            let biome = Biome::from_climate(0.2, 0.0, rainfall, &params);
+            assert_eq!(Biome::ecotone(0.2, 0.0, rainfall, &params), (biome, 0.0));
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use std::{
+    fmt::{Display, Formatter, Result},
+    path::Path
+};
+use noise::{Fbm, SuperSimplex};
+use serde_json::Value;
+use crate::map::{
+    world::WorldParameters,
+    guide::Shape,
+    normalize::Normalization
+};
+
+/// Fields that parse but are never read during generation.
+const UNUSED_FIELDS: [&str; 1] = ["desert_t"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParameterIssue {
+    pub severity: Severity,
+    pub field: String,
+    pub problem: String,
+    pub suggestion: String,
+}
+
+impl Display for ParameterIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{} in `{}`: {} ({})", severity, self.field, self.problem, self.suggestion)
+    }
+}
+
+struct Issues(Vec<ParameterIssue>);
+
+impl Issues {
+    fn push(&mut self, severity: Severity, field: &str, problem: String, suggestion: String) {
+        self.0.push(ParameterIssue { severity, field: field.to_string(), problem, suggestion });
+    }
+
+    fn error(&mut self, field: &str, problem: String, suggestion: String) {
+        self.push(Severity::Error, field, problem, suggestion);
+    }
+
+    fn warning(&mut self, field: &str, problem: String, suggestion: String) {
+        self.push(Severity::Warning, field, problem, suggestion);
+    }
+
+    /// Checks that each named value is strictly greater than the previous one.
+    fn ascending(&mut self, values: &[(&str, f64)]) {
+        for pair in values.windows(2) {
+            let ((low_name, low), (high_name, high)) = (pair[0], pair[1]);
+            if low >= high {
+                self.error(
+                    high_name,
+                    format!("{} must be greater than {} ({})", high, low_name, low),
+                    format!("raise `{}` above {} or lower `{}`", high_name, low, low_name)
+                );
+            }
+        }
+    }
+
+    fn positive(&mut self, field: &str, value: f64) {
+        if value <= 0.0 || !value.is_finite() {
+            self.error(field, format!("{} must be a positive number", value), "use a value above 0.0".to_string());
+        }
+    }
+
+    fn fraction(&mut self, field: &str, value: f64) {
+        if !(0.0..=1.0).contains(&value) {
+            self.error(field, format!("{} is not within [0.0, 1.0]", value), "use a fraction between 0.0 and 1.0".to_string());
+        }
+    }
+}
+
+/// Warns about every key of `raw`, at any depth, that parsing dropped. `parsed`
+/// is the parsed parameters serialized back, so it holds exactly the keys the
+/// structs understand.
+fn unknown_fields(issues: &mut Issues, path: &str, raw: &Value, parsed: &Value) {
+    let field = |key: String| if path.is_empty() { key } else { format!("{}.{}", path, key) };
+    match (raw, parsed) {
+        (Value::Object(raw), Value::Object(parsed)) => {
+            for (key, value) in raw {
+                match parsed.get(key) {
+                    Some(known) => unknown_fields(issues, &field(key.clone()), value, known),
+                    None => issues.warning(&field(key.clone()), "unknown field is ignored".to_string(), "check it for typos".to_string()),
+                }
+            }
+        },
+        (Value::Array(raw), Value::Array(parsed)) => {
+            for (i, (value, known)) in raw.iter().zip(parsed).enumerate() {
+                unknown_fields(issues, &format!("{}[{}]", path, i), value, known);
+            }
+        },
+        _ => {}
+    }
+}
+
+fn validate_normalization(issues: &mut Issues, field: &str, strategy: &Normalization) {
+    let field = format!("normalization.{}", field);
+    match strategy {
+        Normalization::MinMax => {},
+        Normalization::PercentileClamp { low, high } => {
+            issues.fraction(&format!("{}.low", field), *low);
+            issues.fraction(&format!("{}.high", field), *high);
+            if low >= high {
+                issues.error(&field, format!("low ({}) must be below high ({})", low, high), "e.g. low 0.02, high 0.98".to_string());
+            }
+        },
+        Normalization::Equalize { bins } => {
+            if *bins == 0 {
+                issues.error(&format!("{}.bins", field), "bins must be at least 1".to_string(), "the default is 256".to_string());
+            }
+        },
+        Normalization::Curve { points } => {
+            if points.len() < 2 {
+                issues.error(&format!("{}.points", field), format!("{} point(s) given", points.len()), "give at least two [input, output] points".to_string());
+            }
+        }
+    }
+}
+
+/// Checks a parsed parameter set and the raw JSON it came from. Height
+/// thresholds placed by calibration are checked later by `validate_thresholds`.
+/// Returns the warnings if generation can go ahead, or every issue if it cannot.
+pub fn validate(params: &WorldParameters, raw: &Value) -> std::result::Result<Vec<ParameterIssue>, Vec<ParameterIssue>> {
+    let mut issues = Issues(Vec::new());
+
+    let parsed = serde_json::to_value(params).unwrap_or(Value::Null);
+    unknown_fields(&mut issues, "", raw, &parsed);
+    if let Some(object) = raw.as_object() {
+        for key in UNUSED_FIELDS {
+            if object.contains_key(key) {
+                issues.warning(key, "declared but never read during generation".to_string(), "remove it; it has no effect".to_string());
+            }
+        }
+    }
+
+    let quantiles = [("hills_q", params.hills_q), ("mountain_q", params.mountain_q), ("peak_q", params.peak_q)];
+    if quantiles.iter().all(|(_, q)| q.is_none()) {
+        issues.ascending(&[
+            ("sea_level", params.sea_level),
+            ("hills_h", params.hills_h),
+            ("mountain_h", params.mountain_h),
+            ("peak_h", params.peak_h),
+        ]);
+    } else {
+        let given = quantiles.iter()
+            .filter_map(|(name, q)| q.map(|q| (*name, q)))
+            .collect::<Vec<(&str, f64)>>();
+        given.iter().for_each(|(name, q)| issues.fraction(name, *q));
+        issues.ascending(&given);
+    }
+
+    issues.ascending(&[
+        ("frozen_t", params.frozen_t),
+        ("tundra_t", params.tundra_t),
+        ("boreal_t", params.boreal_t),
+    ]);
+
+    issues.positive("global_heat_scaling", params.global_heat_scaling);
+    issues.positive("altitude_scale", params.altitude_scale);
+    issues.positive("temperature_scale", params.temperature_scale);
+    issues.positive("rainfall_scale", params.rainfall_scale);
+
+    for (layer, stack) in params.noise.layers() {
+        if !(1..=Fbm::<SuperSimplex>::MAX_OCTAVES).contains(&stack.octaves) {
+            issues.error(
+                &format!("noise.{}.octaves", layer),
+                format!("{} octaves", stack.octaves),
+                format!("use between 1 and {} octaves", Fbm::<SuperSimplex>::MAX_OCTAVES)
+            );
+        }
+        issues.positive(&format!("noise.{}.frequency", layer), stack.frequency);
+        issues.positive(&format!("noise.{}.lacunarity", layer), stack.lacunarity);
+        issues.positive(&format!("noise.{}.persistence", layer), stack.persistence);
+    }
+
+    if params.ecotone_width < 0.0 {
+        issues.error("ecotone_width", format!("{} is negative", params.ecotone_width), "use 0.0 for hard biome borders".to_string());
+    }
+
+    if let Some(ratio) = params.target_land_ratio {
+        issues.fraction("target_land_ratio", ratio);
+    }
+
+    if let Some(guide) = &params.guide {
+        if guide.strength < 0.0 {
+            issues.error("guide.strength", format!("{} is negative", guide.strength), "use a value of 0.0 or above".to_string());
+        }
+        if guide.falloff < 0.0 {
+            issues.error("guide.falloff", format!("{} is negative", guide.falloff), "use a value of 0.0 or above".to_string());
+        }
+        if let Some(path) = guide.mask.as_ref().filter(|path| !Path::new(path).is_file()) {
+            issues.error("guide.mask", format!("'{}' is not a file", path), "give the path of a grayscale image".to_string());
+        }
+        for (i, shape) in guide.shapes.iter().enumerate() {
+            let field = format!("guide.shapes[{}]", i);
+            match shape {
+                Shape::Ellipse { rx, ry, .. } => {
+                    issues.positive(&format!("{}.rx", field), *rx);
+                    issues.positive(&format!("{}.ry", field), *ry);
+                },
+                Shape::Polygon { points, .. } => {
+                    if points.len() < 3 {
+                        issues.error(&format!("{}.points", field), format!("{} point(s) given", points.len()), "a polygon needs at least three points".to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(cleanup) = &params.cleanup {
+        for (field, value) in [("cleanup.birth", cleanup.birth), ("cleanup.survival", cleanup.survival)] {
+            if value > 8 {
+                issues.error(field, format!("{} is more than the 8 neighbors a tile has", value), "use a count between 0 and 8".to_string());
+            }
+        }
+    }
+
+    if let Some(ice) = &params.ice {
+        issues.ascending(&[("ice.snowline_pole", ice.snowline_pole), ("ice.snowline_equator", ice.snowline_equator)]);
+        if ice.melt < 0.0 {
+            issues.error("ice.melt", format!("{} is negative", ice.melt), "use a value of 0.0 or above".to_string());
+        }
+    }
+
+    if let Some(volcanism) = &params.volcanism {
+        issues.fraction("volcanism.active_share", volcanism.active_share);
+        issues.positive("volcanism.spacing", volcanism.spacing);
+        issues.positive("volcanism.cone_radius", volcanism.cone_radius);
+        issues.positive("volcanism.fertility_radius", volcanism.fertility_radius);
+    }
+
+    if let Some(hazards) = &params.hazards {
+        issues.positive("hazards.flood_height", hazards.flood_height);
+        issues.positive("hazards.drought_band", hazards.drought_band);
+        issues.positive("hazards.eruption_radius", hazards.eruption_radius);
+        issues.positive("hazards.fault_scale", hazards.fault_scale);
+        issues.positive("hazards.fault_width", hazards.fault_width);
+        hazards.rates.iter().for_each(|rate| issues.fraction("hazards.rates", *rate));
+    }
+
+    for (i, forcing) in params.climate.iter().flatten().enumerate() {
+        let field = format!("climate[{}]", i);
+        if forcing.duration == 0 {
+            issues.error(&format!("{}.duration", field), "a forcing must last at least one year".to_string(), "use a duration of 1 or more".to_string());
+        }
+        if let Some([x, y, radius]) = forcing.region {
+            issues.fraction(&format!("{}.region", field), x);
+            issues.fraction(&format!("{}.region", field), y);
+            issues.positive(&format!("{}.region", field), radius);
+        }
+    }
+
+    let normalization = &params.normalization;
+    [("altitude", &normalization.altitude), ("temperature", &normalization.temperature), ("rainfall", &normalization.rainfall)]
+        .iter()
+        .for_each(|(field, strategy)| {
+            if let Some(strategy) = strategy { validate_normalization(&mut issues, field, strategy); }
+        });
+
+    let issues = issues.0;
+    if issues.iter().any(|issue| issue.severity == Severity::Error) { Err(issues) }
+    else { Ok(issues) }
+}
+
+/// Smallest gap kept between height thresholds that had to be raised.
+const THRESHOLD_GAP: f64 = 1e-6;
+
+/// Checks the height thresholds once calibration has placed them, since
+/// quantiles and a calibrated sea level are only known after sampling. Any
+/// threshold at or below the one before it is raised just above it, so every
+/// band keeps its order; the returned warnings list what was out of order.
+pub fn validate_thresholds(params: &mut WorldParameters) -> Vec<ParameterIssue> {
+    let mut issues = Issues(Vec::new());
+    issues.ascending(&[
+        ("sea_level", params.sea_level),
+        ("hills_h", params.hills_h),
+        ("mountain_h", params.mountain_h),
+        ("peak_h", params.peak_h),
+    ]);
+
+    let mut floor = params.sea_level;
+    for threshold in [&mut params.hills_h, &mut params.mountain_h, &mut params.peak_h] {
+        if *threshold <= floor { *threshold = floor + THRESHOLD_GAP; }
+        floor = *threshold;
+    }
+
+    issues.0.into_iter()
+        .map(|issue| ParameterIssue { severity: Severity::Warning, ..issue })
+        .collect()
+}
+
+pub fn report(issues: &[ParameterIssue]) -> String {
+    issues.iter()
+        .map(|issue| format!("[MapGen]   {}", issue))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::map::presets::{preset_names, resolve};
+    use super::*;
+
+    fn unknown(value: Value) -> Vec<String> {
+        let value = resolve(value, &mut Vec::new()).unwrap();
+        let params = serde_json::from_value::<WorldParameters>(value.clone()).unwrap();
+        let issues = validate(&params, &value).unwrap();
+        issues.into_iter()
+            .filter(|issue| issue.problem == "unknown field is ignored")
+            .map(|issue| issue.field)
+            .collect()
+    }
+
+    #[test]
+    fn presets_have_no_unknown_fields() {
+        for name in preset_names() {
+            assert_eq!(unknown(json!({ "inherits": name })), Vec::<String>::new(), "preset '{}'", name);
+        }
+    }
+
+    #[test]
+    fn missing_mask_images_are_errors() {
+        let value = resolve(json!({ "inherits": "pangaea", "guide": { "mask": "/nope.png" } }), &mut Vec::new()).unwrap();
+        let params = serde_json::from_value::<WorldParameters>(value.clone()).unwrap();
+        let issues = validate(&params, &value).unwrap_err();
+        assert!(issues.iter().any(|issue| issue.field == "guide.mask"));
+    }
+
+    #[test]
+    fn nested_typos_are_reported() {
+        let fields = unknown(json!({
+            "inherits": "pangaea",
+            "sea_levle": 0.1,
+            "ice": { "flow_pases": 3 },
+            "hazards": { "eruption_radius": 4.0, "eruption_radus": 5.0 },
+            "cleanup": { "min_lake": 4 },
+            "volcanism": { "hotspot": 2 },
+            "normalization": { "altitude": { "strategy": "equalize", "bin": 64 } },
+            "climate": [{ "kind": "megadrought", "start": 0, "duration": 10, "strength": 0.2, "regoin": [0.5, 0.5, 0.1] }],
+            "guide": { "strenght": 0.2, "shapes": [{ "type": "ellipse", "x": 0.5, "y": 0.5, "rx": 0.2, "ry": 0.2, "vaule": 1.0 }] }
+        }));
+        let mut expected = [
+            "sea_levle", "ice.flow_pases", "hazards.eruption_radus", "cleanup.min_lake",
+            "volcanism.hotspot", "normalization.altitude.bin", "climate[0].regoin",
+            "guide.strenght", "guide.shapes[0].vaule",
+        ].map(String::from).to_vec();
+        expected.sort();
+        let mut fields = fields;
+        fields.sort();
+        assert_eq!(fields, expected);
+    }
+}
@@ -0,0 +1,154 @@
+use std::f64::consts::PI;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::map::{
+    tile::Tile,
+    world::{Viewport, WorldParameters}
+};
+
+/// Summit height lost per step down a hotspot chain, as a share of `cone_height`.
+const EROSION: f64 = 0.2;
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct VolcanismParameters {
+    /// Number of hotspots, each leaving a chain of volcanoes behind as the crust drifts
+    #[serde(default = "default_hotspots")]
+    pub hotspots: usize,
+    /// Volcanoes per hotspot chain; only the youngest is active
+    #[serde(default = "default_chain_length")]
+    pub chain_length: usize,
+    /// Distance between volcanoes along a chain, in world units
+    #[serde(default = "default_spacing")]
+    pub spacing: f64,
+    /// Number of curved island arcs
+    #[serde(default = "default_arcs")]
+    pub arcs: usize,
+    /// Volcanoes per arc
+    #[serde(default = "default_arc_volcanoes")]
+    pub arc_volcanoes: usize,
+    /// Share of arc volcanoes that are active
+    #[serde(default = "default_active_share")]
+    pub active_share: f64,
+    /// Summit height above sea level of the youngest volcanoes
+    #[serde(default = "default_cone_height")]
+    pub cone_height: f64,
+    /// Cone base radius, in world units
+    #[serde(default = "default_cone_radius")]
+    pub cone_radius: f64,
+    /// Radius of the fertile ash soils around each volcano, in world units
+    #[serde(default = "default_fertility_radius")]
+    pub fertility_radius: f64,
+    /// Fertility added right next to a volcano, fading out to `fertility_radius`
+    #[serde(default = "default_fertility_boost")]
+    pub fertility_boost: f64,
+}
+
+fn default_hotspots() -> usize { 3 }
+fn default_chain_length() -> usize { 5 }
+fn default_spacing() -> f64 { 12.0 }
+fn default_arcs() -> usize { 2 }
+fn default_arc_volcanoes() -> usize { 6 }
+fn default_active_share() -> f64 { 0.4 }
+fn default_cone_height() -> f64 { 0.8 }
+fn default_cone_radius() -> f64 { 4.0 }
+fn default_fertility_radius() -> f64 { 10.0 }
+fn default_fertility_boost() -> f64 { 0.5 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Volcano {
+    Active,
+    Dormant,
+}
+
+/// A volcano in world coordinates.
+#[derive(Debug, Clone, Copy)]
+struct Vent {
+    x: f64,
+    y: f64,
+    state: Volcano,
+    /// Summit height as a share of `cone_height`
+    height: f64,
+}
+
+/// Places every vent of the full world, so zoomed regions and chunks built
+/// from the same seed see the same volcanoes.
+fn vents(params: &VolcanismParameters, seed: u64, width: f64, height: f64) -> Vec<Vent> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut vents = Vec::new();
+
+    // Hotspots share one drift direction; their chains trail behind it
+    let drift = rng.gen_range(0.0..2.0 * PI);
+    for _ in 0..params.hotspots {
+        let (x, y) = (rng.gen_range(0.0..width), rng.gen_range(0.0..height));
+        for age in 0..params.chain_length {
+            let distance = age as f64 * params.spacing;
+            vents.push(Vent {
+                x: x - drift.cos() * distance,
+                y: y - drift.sin() * distance,
+                state: if age == 0 { Volcano::Active } else { Volcano::Dormant },
+                height: 1.0 - EROSION * age as f64,
+            });
+        }
+    }
+
+    for _ in 0..params.arcs {
+        let (cx, cy) = (rng.gen_range(0.0..width), rng.gen_range(0.0..height));
+        let radius = params.spacing * params.arc_volcanoes as f64 / 2.0;
+        let start = rng.gen_range(0.0..2.0 * PI);
+        for i in 0..params.arc_volcanoes {
+            let angle = start + i as f64 * params.spacing / radius;
+            vents.push(Vent {
+                x: cx + angle.cos() * radius,
+                y: cy + angle.sin() * radius,
+                state: if rng.gen_bool(params.active_share.clamp(0.0, 1.0)) { Volcano::Active } else { Volcano::Dormant },
+                height: rng.gen_range(0.6..1.0),
+            });
+        }
+    }
+    vents
+}
+
+/// Raises volcanic cones, marks the tile under each vent and enriches the
+/// soil around it. Tiles whose altitude changed are reclassified.
+/// Returns the number of volcanoes inside the viewport.
+pub fn raise_volcanoes(
+    tiles: &mut [Tile],
+    viewport: &Viewport,
+    params: &WorldParameters,
+    seed: u64,
+    domain: (f64, f64)
+) -> usize {
+    let volcanism = match &params.volcanism {
+        Some(volcanism) => volcanism,
+        None => return 0,
+    };
+    let vents = vents(volcanism, seed, domain.0, domain.1);
+    // Half a tile, in world units, so exactly one tile falls under each vent
+    let crater = 0.5 / viewport.scale;
+    let mut placed = 0;
+
+    for tile in tiles.iter_mut() {
+        let (sx, sy) = viewport.sample(tile.x, tile.y);
+        let mut summit = f64::MIN;
+        for vent in &vents {
+            let distance = ((sx - vent.x).powi(2) + (sy - vent.y).powi(2)).sqrt();
+            if distance < volcanism.cone_radius {
+                let profile = 1.0 - distance / volcanism.cone_radius;
+                summit = summit.max(params.sea_level + volcanism.cone_height * vent.height * profile * profile);
+            }
+            if distance < volcanism.fertility_radius {
+                tile.fertility += volcanism.fertility_boost * (1.0 - distance / volcanism.fertility_radius);
+            }
+            if (sx - vent.x).abs() < crater && (sy - vent.y).abs() < crater {
+                tile.volcano = Some(vent.state);
+                placed += 1;
+            }
+        }
+        if summit > tile.altitude {
+            tile.altitude = summit;
+            tile.classify(params);
+        }
+    }
+    placed
+}
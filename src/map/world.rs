@@ -2,15 +2,27 @@ use std::sync::{
     Arc,
     mpsc::channel
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use threadpool::ThreadPool;
-use rand::Rng;
 use crate::{
-    map::tile::{Tile, Biome},
-    utils::{cli::Args, helpers::adjacent},
+    map::{
+        tile::{Tile, Biome},
+        guide::{AltitudeGuides, LandGuide},
+        hazards::{assess, HazardEvent, HazardParameters},
+        noise_stack::NoiseStacks,
+        normalize::{NormalizationParameters, NormalizationRecord},
+        cleanup::{cleanup, CleanupParameters},
+        climate::{ClimateHistory, Forcing},
+        fauna::populate,
+        features::{detect_features, StrategicFeature},
+        ice::{generate_ice, IceParameters},
+        volcanism::{raise_volcanoes, VolcanismParameters},
+        validation::{report, validate_thresholds}
+    },
+    utils::{cli::Args, helpers::{adjacent, quantile}, seed::WorldSeed},
 };
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct WorldParameters {
     pub sea_level: f64,
     pub peak_h: f64,
@@ -25,121 +37,307 @@ pub struct WorldParameters {
     pub rainforest_t: f64,
     pub rainforest_r: f64,
     pub wetlands_r: f64,
+    /// Not read by `Biome::from_climate`; kept so older parameter files still parse
+    #[serde(default)]
     pub desert_t: f64,
     pub desert_r: f64,
     pub plains_cutoff: f64,
     pub global_heat_scaling: f64,
     pub altitude_scale: f64,
     pub temperature_scale: f64,
-    pub rainfall_scale: f64
+    pub rainfall_scale: f64,
+    /// Octaves, frequency, lacunarity and persistence of each noise layer
+    #[serde(default)]
+    pub noise: NoiseStacks,
+    #[serde(default)]
+    pub guide: Option<LandGuide>,
+    /// Fraction of tiles that should end up as land; overrides `sea_level`, and
+    /// moves the height thresholds along with it
+    #[serde(default)]
+    pub target_land_ratio: Option<f64>,
+    /// Quantiles of land altitude; override `hills_h`, `mountain_h` and `peak_h`
+    #[serde(default)]
+    pub hills_q: Option<f64>,
+    #[serde(default)]
+    pub mountain_q: Option<f64>,
+    #[serde(default)]
+    pub peak_q: Option<f64>,
+    #[serde(default)]
+    pub normalization: NormalizationParameters,
+    /// Coastline smoothing and small island/lake removal before coast detection
+    #[serde(default)]
+    pub cleanup: Option<CleanupParameters>,
+    /// Climate distance over which neighboring biomes blend; the default 0.0 keeps
+    /// the hard borders older parameter files were made with
+    #[serde(default)]
+    pub ecotone_width: f64,
+    /// Ice sheets, glaciers, snowlines and sea ice; no ice is placed if absent
+    #[serde(default)]
+    pub ice: Option<IceParameters>,
+    /// Hotspot chains and island arcs; no volcanoes are placed if absent
+    #[serde(default)]
+    pub volcanism: Option<VolcanismParameters>,
+    /// Flood, drought, eruption and earthquake risk; no hazards fire if absent
+    #[serde(default)]
+    pub hazards: Option<HazardParameters>,
+    /// Ice ages, warm periods and megadroughts over simulated years
+    #[serde(default)]
+    pub climate: Option<Vec<Forcing>>,
+}
+
+/// Record of how a world was post-processed after noise sampling.
+#[derive(Clone, Default, Serialize)]
+pub struct WorldMetadata {
+    pub normalization: Vec<NormalizationRecord>,
+}
+
+/// Maps tile grid coordinates onto world coordinates, where the noise is sampled.
+/// The full world has its origin at (0, 0) and one tile per world unit.
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport {
+    pub x: f64,
+    pub y: f64,
+    pub scale: f64,
+    pub equator: f64,
+    /// Size of the full world, which map fractions are relative to
+    pub extent: (f64, f64),
+}
+
+impl Viewport {
+    pub fn sample(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.x + x / self.scale, self.y + y / self.scale)
+    }
 }
 
 pub struct WorldBuilder<'a> {
     pub args: &'a Args,
-    pub params: &'a WorldParameters,
+    pub params: WorldParameters,
+    pub seed: WorldSeed,
     pub seeds: [u32; 4],
     pub width: usize,
     pub height: usize,
     pub size: usize,
-    pub equator: f64,
+    pub viewport: Viewport,
+    pub guides: Arc<AltitudeGuides>,
+    pub metadata: WorldMetadata,
     pub tiles: Vec<Tile>, 
     pub rivers: Vec<usize>,
 }
 
 pub struct World {
+    pub params: WorldParameters,
+    pub metadata: WorldMetadata,
+    pub seed: WorldSeed,
     pub seeds: [u32; 4],
     pub width: usize,
     pub height: usize,
     pub size: usize,
-    pub equator: f64,
+    pub viewport: Viewport,
+    pub guides: Arc<AltitudeGuides>,
     pub tiles: Vec<Tile>, 
     pub rivers: Vec<usize>,
+    pub features: Vec<StrategicFeature>,
+    /// Simulated years since generation
+    pub year: u32,
+    pub events: Vec<HazardEvent>,
+    pub climate: ClimateHistory,
 }
 
 impl<'a> From<&mut WorldBuilder<'a>> for World {
     fn from(builder: &mut WorldBuilder<'a>) -> Self {
         World {
+            params: builder.params.clone(),
+            metadata: builder.metadata.clone(),
+            seed: builder.seed.clone(),
             seeds: builder.seeds,
             width: builder.width,
             height: builder.height,
             size: builder.size,
-            equator: builder.equator,
+            viewport: builder.viewport,
+            guides: builder.guides.clone(),
             tiles: builder.tiles.clone(),
-            rivers: builder.rivers.clone()
+            rivers: builder.rivers.clone(),
+            features: Vec::new(),
+            year: 0,
+            events: Vec::new(),
+            climate: ClimateHistory::default()
         }
     }
 }
 
+impl World {
+    /// Regenerates the area around (u, v), given as fractions of this world's
+    /// width and height, at `factor` times the current resolution.
+    pub fn zoom(&self, args: &Args, u: f64, v: f64, factor: f64) -> World {
+        let (span_w, span_h) = (
+            self.width as f64 / self.viewport.scale,
+            self.height as f64 / self.viewport.scale
+        );
+        let (w, h) = (span_w / factor, span_h / factor);
+        let x = (self.viewport.x + u * span_w - w / 2.0)
+            .clamp(self.viewport.x, self.viewport.x + span_w - w);
+        let y = (self.viewport.y + v * span_h - h / 2.0)
+            .clamp(self.viewport.y, self.viewport.y + span_h - h);
+
+        WorldBuilder::from_world(args, self)
+            .region(x, y, w, h, self.viewport.scale * factor)
+            .build()
+    }
+}
+
+/// Turns every land tile next to the sea into coast.
+pub fn mark_coast(tiles: &mut [Tile], width: usize) {
+    let size = tiles.len();
+    let mut coast_tiles = Vec::<usize>::new();
+
+    tiles.iter().filter(|tile| tile.is_sea()).for_each(|tile| {
+        adjacent(tile.id, width, size).iter().for_each(|neighbor| {
+            if !&tiles[*neighbor].is_sea() {
+                coast_tiles.push(*neighbor);
+            };
+        });
+    });
+
+    coast_tiles.iter().for_each(|t| {
+        tiles[*t].biome = Biome::Coast;
+        tiles[*t].blend = 0.0;
+    });
+}
+
 impl<'a> WorldBuilder<'a> {
-    pub fn new(args: &'a Args, params: &'a WorldParameters) -> WorldBuilder<'a> {
-        let mut rng = rand::thread_rng();
-        let seeds: [u32; 4] = match &args.seeds {
-            None => [
-                rng.gen::<u32>(),
-                rng.gen::<u32>(),
-                rng.gen::<u32>(),
-                rng.gen::<u32>()
-            ],
-            Some(j) => [j[0], j[1], j[2], j[3]],
-        };
+    /// `guides` are those loaded by `AltitudeGuides::load` for `params`.
+    pub fn new(args: &'a Args, params: &WorldParameters, guides: &Arc<AltitudeGuides>) -> Result<WorldBuilder<'a>, String> {
+        let seed = WorldSeed::from_args(args)?;
+        Ok(WorldBuilder::seeded(args, params, seed, guides.clone()))
+    }
+
+    fn seeded(args: &'a Args, params: &WorldParameters, seed: WorldSeed, guides: Arc<AltitudeGuides>) -> WorldBuilder<'a> {
+        let seeds = seed.layers();
 
         WorldBuilder { 
             args,
-            params,
+            params: params.clone(),
+            seed,
             seeds,
             width: args.x,
             height: args.y,
             size: args.x * args.y,
-            equator: (args.y / 2) as f64,
-            tiles: Vec::with_capacity(args.x * args.y),
+            viewport: Viewport {
+                x: 0.0,
+                y: 0.0,
+                scale: 1.0,
+                equator: (args.y / 2) as f64,
+                extent: (args.x as f64, args.y as f64)
+            },
+            guides,
+            metadata: WorldMetadata::default(),
+            tiles: Vec::new(),
             rivers: Vec::new()
         }
     }
 
+    /// Starts from an existing world's seeds, effective parameters and fitted
+    /// normalizations, so regions generated from it match its features.
+    pub fn from_world(args: &'a Args, world: &World) -> WorldBuilder<'a> {
+        let mut builder = WorldBuilder::seeded(args, &world.params, world.seed.clone(), world.guides.clone());
+        builder.seeds = world.seeds;
+        builder.metadata = world.metadata.clone();
+        builder.params.target_land_ratio = None;
+        builder.params.hills_q = None;
+        builder.params.mountain_q = None;
+        builder.params.peak_q = None;
+        builder
+    }
+
+    pub fn with_seed(&mut self, seed: &WorldSeed) -> &mut Self {
+        self.seed = seed.clone();
+        self.seeds = seed.layers();
+        self
+    }
+
+    /// Restricts generation to the window (x, y, w, h) in world coordinates,
+    /// sampled at `scale` tiles per world unit.
+    pub fn region(&mut self, x: f64, y: f64, w: f64, h: f64, scale: f64) -> &mut Self {
+        self.width = (w * scale).round().max(1.0) as usize;
+        self.height = (h * scale).round().max(1.0) as usize;
+        self.size = self.width * self.height;
+        self.viewport = Viewport { x, y, scale, ..self.viewport };
+        self.tiles = Vec::new();
+        self
+    }
+
     pub fn build(&mut self) -> World {
+        if self.viewport.scale != 1.0 || self.viewport.x != 0.0 || self.viewport.y != 0.0 {
+            println!("[MapGen] Building region at ({:.1}, {:.1}) in {} x {}, scale {}.",
+                self.viewport.x,
+                self.viewport.y,
+                self.width,
+                self.height,
+                self.viewport.scale
+            );
+        }
         println!("[MapGen] Building world using seeds [{}, {}, {}, {}]",
             self.seeds[0],
             self.seeds[1],
             self.seeds[2],
             self.seeds[3]
         );
-        self.generate_tiles()
+        println!("[MapGen] Reproduce this world with --seed \"{}\" -x {} -y {}",
+            self.seed,
+            self.args.x,
+            self.args.y
+        );
+        let mut world: World = self.calibrate()
+            .cleanup_coast()
+            .raise_volcanoes()
             .generate_coast()
+            .generate_ice()
+            .populate_fauna()
+            .assess_hazards()
             //.generate_rivers()
-            .into()
+            .into();
+        world.features = detect_features(&world);
+        world
+    }
+
+    /// Samples every tile, then fits the normalizations and thresholds that
+    /// need the whole world at once. Every later stage only looks at a
+    /// tile's surroundings.
+    pub fn calibrate(&mut self) -> &mut Self {
+        self.generate_tiles()
+            .normalize_fields()
+            .calibrate_thresholds()
     }
 
     fn generate_tiles(&mut self) -> &mut Self {
         println!("[MapGen] Building tiles.");
-        let noise = [
-            noise::Fbm::<noise::SuperSimplex>::new(self.seeds[0]),
-            noise::Fbm::<noise::SuperSimplex>::new(self.seeds[1]),
-            noise::Fbm::<noise::SuperSimplex>::new(self.seeds[2]),
-            noise::Fbm::<noise::SuperSimplex>::new(self.seeds[3])
-        ];
+        let noise = self.params.noise.build(self.seeds);
 
         let noise = Arc::new(noise);
         let parameters = Arc::new(self.params.clone());
+        let guides = self.guides.clone();
         let workers = 32;
         let pool = ThreadPool::new(workers);
         let (tx, rx) = channel::<Tile>();
 
         let (dim_x, dim_y) = { ((0..self.width), (0..self.height)) };
         let width = self.width;
-        let equator = self.equator;
+        let viewport = self.viewport;
         for x in dim_x {
             for y in dim_y.clone() {
                 let tx = tx.clone();
                 let noise = noise.clone();
                 let parameters = parameters.clone();
+                let guides = guides.clone();
                 pool.execute(move || {
                     let tile = Tile::new(
                         x + width * y,
                         x as f64,
                         y as f64,
-                        &equator,
+                        &viewport,
                         &noise,
                         &parameters,
+                        &guides,
                     );
                     tx.send(tile).unwrap();
                 });
@@ -147,28 +345,149 @@ impl<'a> WorldBuilder<'a> {
         }
 
         drop(tx);
-        let mut tiles = rx.iter().collect::<Vec<Tile>>();
+        let mut tiles = Vec::with_capacity(self.size);
+        tiles.extend(rx.iter());
         tiles.sort();
         self.tiles = tiles;
         println!("[MapGen] Tiles built.");
         self
     }
 
+    fn normalize_fields(&mut self) -> &mut Self {
+        if !self.metadata.normalization.is_empty() {
+            println!("[MapGen] Reapplying inherited normalization.");
+            let records = &self.metadata.normalization;
+            let params = &self.params;
+            self.tiles.iter_mut().for_each(|tile| {
+                records.iter().for_each(|record| record.apply(tile));
+                tile.classify(params);
+            });
+            return self;
+        }
+
+        let fields = self.params.normalization.fields();
+        if fields.is_empty() { return self; }
+
+        println!("[MapGen] Normalizing fields.");
+        for (field, strategy) in fields {
+            let record = NormalizationRecord::fit(field, strategy, &self.tiles);
+            println!("[MapGen] Normalized {:?} from [{:.4}, {:.4}] using {:?}.",
+                field,
+                record.source_range.0,
+                record.source_range.1,
+                record.strategy
+            );
+            self.tiles.iter_mut().for_each(|tile| record.apply(tile));
+            self.metadata.normalization.push(record);
+        }
+
+        let params = &self.params;
+        self.tiles.iter_mut().for_each(|tile| tile.classify(params));
+        self
+    }
+
+    fn calibrate_thresholds(&mut self) -> &mut Self {
+        let params = &mut self.params;
+        if params.target_land_ratio.is_none()
+            && params.hills_q.is_none()
+            && params.mountain_q.is_none()
+            && params.peak_q.is_none() { return self; }
+
+        println!("[MapGen] Calibrating altitude thresholds.");
+        let mut altitudes = self.tiles.iter().map(|tile| tile.altitude).collect::<Vec<f64>>();
+        altitudes.sort_by(f64::total_cmp);
+
+        if let Some(ratio) = params.target_land_ratio {
+            let configured = params.sea_level;
+            let sea_tiles = ((1.0 - ratio.clamp(0.0, 1.0)) * altitudes.len() as f64).round() as usize;
+            params.sea_level = match sea_tiles {
+                0 => altitudes[0] - f64::EPSILON,
+                n => altitudes[n - 1]
+            };
+            println!("[MapGen] Sea level set to {:.4} for a target land ratio of {:.1}%.",
+                params.sea_level,
+                ratio * 100.0
+            );
+
+            // Absolute height thresholds keep their share of the span above sea
+            // level, so a raised sea never swallows hills or mountains
+            let span = (1.0 - params.sea_level) / (1.0 - configured).max(f64::EPSILON);
+            let rescale = |h: f64| params.sea_level + (h - configured) * span;
+            if params.hills_q.is_none() { params.hills_h = rescale(params.hills_h); }
+            if params.mountain_q.is_none() { params.mountain_h = rescale(params.mountain_h); }
+            if params.peak_q.is_none() { params.peak_h = rescale(params.peak_h); }
+        }
+
+        let land = altitudes.iter()
+            .copied()
+            .filter(|altitude| *altitude > params.sea_level)
+            .collect::<Vec<f64>>();
+        if !land.is_empty() {
+            if let Some(q) = params.hills_q { params.hills_h = quantile(&land, q); }
+            if let Some(q) = params.mountain_q { params.mountain_h = quantile(&land, q); }
+            if let Some(q) = params.peak_q { params.peak_h = quantile(&land, q); }
+        }
+        println!("[MapGen] Thresholds: hills {:.4}, mountains {:.4}, peaks {:.4}.",
+            params.hills_h,
+            params.mountain_h,
+            params.peak_h
+        );
+        let issues = validate_thresholds(params);
+        if !issues.is_empty() {
+            println!("[MapGen] Calibrated thresholds are out of order and were raised:\n{}", report(&issues));
+        }
+
+        let params = &self.params;
+        self.tiles.iter_mut().for_each(|tile| tile.classify(params));
+        let land = self.tiles.iter().filter(|tile| !tile.is_sea()).count();
+        println!("[MapGen] Land ratio is {:.1}%.", 100.0 * land as f64 / self.size as f64);
+        self
+    }
+
+    fn cleanup_coast(&mut self) -> &mut Self {
+        if self.params.cleanup.is_none() { return self; }
+        println!("[MapGen] Cleaning up coastline.");
+        let scale = self.viewport.scale;
+        let changed = cleanup(&mut self.tiles, self.width, &self.params, scale * scale);
+        println!("[MapGen] Coastline cleanup changed {} tiles.", changed);
+        self
+    }
+
+    fn raise_volcanoes(&mut self) -> &mut Self {
+        if self.params.volcanism.is_none() { return self; }
+        println!("[MapGen] Raising volcanoes.");
+        let domain = (self.args.x as f64, self.args.y as f64);
+        let seed = self.seed.derive("volcanism");
+        let placed = raise_volcanoes(&mut self.tiles, &self.viewport, &self.params, seed, domain);
+        println!("[MapGen] Placed {} volcanoes.", placed);
+        self
+    }
+
+    fn generate_ice(&mut self) -> &mut Self {
+        if self.params.ice.is_none() { return self; }
+        println!("[MapGen] Placing ice.");
+        let iced = generate_ice(&mut self.tiles, self.width, &self.params);
+        println!("[MapGen] {} tiles are covered in ice.", iced);
+        self
+    }
+
+    fn populate_fauna(&mut self) -> &mut Self {
+        println!("[MapGen] Populating fauna.");
+        populate(&mut self.tiles, self.width);
+        self
+    }
+
+    fn assess_hazards(&mut self) -> &mut Self {
+        if self.params.hazards.is_none() { return self; }
+        println!("[MapGen] Assessing hazard risk.");
+        let seed = self.seed.derive("faults");
+        assess(&mut self.tiles, &self.viewport, &self.params, seed);
+        self
+    }
+
     fn generate_coast(&mut self) -> &mut Self {
         println!("[MapGen] Finding coast tiles.");
-        let mut coast_tiles = Vec::<usize>::new();
-
-        self.tiles.iter().filter(|tile| tile.is_sea()).for_each(|tile| {
-            adjacent(tile.id, self.width, self.size).iter().for_each(|neighbor| {
-                if !&self.tiles[*neighbor].is_sea() {
-                    coast_tiles.push(*neighbor);
-                };
-            });
-        });
-        
-        coast_tiles.iter().for_each(|t| {
-            self.tiles[*t].biome = Biome::Coast;
-        });
+        mark_coast(&mut self.tiles, self.width);
         println!("[MapGen] Coast processed.");
         self
     }
@@ -197,3 +516,79 @@ impl<'a> WorldBuilder<'a> {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use crate::{map::presets::load_preset, utils::cli::Args};
+    use super::*;
+
+    fn land_ratio(builder: &WorldBuilder) -> f64 {
+        builder.tiles.iter().filter(|tile| !tile.is_sea()).count() as f64 / builder.size as f64
+    }
+
+    #[test]
+    fn calibration_hits_target_land_ratio() {
+        let args = Args::parse_from(["civ-sim", "-x", "80", "-y", "60", "-s", "calibration"]);
+        for ratio in [0.1, 0.35, 0.8] {
+            let mut params = load_preset("earthlike").unwrap();
+            params.target_land_ratio = Some(ratio);
+            let mut builder = WorldBuilder::new(&args, &params, &Arc::new(AltitudeGuides::load(&args, &params).unwrap())).unwrap();
+            builder.generate_tiles().calibrate_thresholds();
+            assert!((land_ratio(&builder) - ratio).abs() < 1.0 / builder.size as f64, "target {}", ratio);
+        }
+    }
+
+    #[test]
+    fn raised_sea_level_keeps_height_thresholds_above_it() {
+        let args = Args::parse_from(["civ-sim", "-x", "80", "-y", "60", "-s", "calibration"]);
+        let mut params = load_preset("earthlike").unwrap();
+        (params.hills_h, params.mountain_h, params.peak_h) = (0.02, 0.04, 0.06);
+        params.target_land_ratio = Some(0.05);
+        let mut builder = WorldBuilder::new(&args, &params, &Arc::new(AltitudeGuides::load(&args, &params).unwrap())).unwrap();
+        builder.generate_tiles().calibrate_thresholds();
+
+        let params = &builder.params;
+        assert!(params.sea_level < params.hills_h);
+        assert!(params.hills_h < params.mountain_h && params.mountain_h < params.peak_h);
+        assert!((land_ratio(&builder) - 0.05).abs() < 1.0 / builder.size as f64);
+        assert!(builder.tiles.iter().all(|tile| tile.is_sea() == (tile.altitude <= params.sea_level)));
+    }
+
+    #[test]
+    fn quantile_thresholds_split_land() {
+        let args = Args::parse_from(["civ-sim", "-x", "80", "-y", "60", "-s", "calibration"]);
+        let mut params = load_preset("earthlike").unwrap();
+        params.target_land_ratio = Some(0.4);
+        (params.hills_q, params.mountain_q, params.peak_q) = (Some(0.5), Some(0.8), Some(0.95));
+        let mut builder = WorldBuilder::new(&args, &params, &Arc::new(AltitudeGuides::load(&args, &params).unwrap())).unwrap();
+        builder.generate_tiles().calibrate_thresholds();
+
+        let land = builder.tiles.iter().filter(|tile| !tile.is_sea()).count() as f64;
+        let highland = builder.tiles.iter()
+            .filter(|tile| matches!(tile.biome, Biome::Hill | Biome::Mountain | Biome::Peak))
+            .count() as f64;
+        assert!((highland / land - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn zoomed_regions_match_the_coarse_map() {
+        let args = Args::parse_from(["civ-sim", "-x", "80", "-y", "60", "-s", "zoom"]);
+        let params = load_preset("earthlike").unwrap();
+        let world = WorldBuilder::new(&args, &params, &Arc::new(AltitudeGuides::load(&args, &params).unwrap())).unwrap().build();
+        let zoomed = world.zoom(&args, 0.5, 0.5, 2.0);
+        assert_eq!((zoomed.viewport.x, zoomed.viewport.y), (20.0, 15.0));
+
+        // Every other zoomed tile falls on a coarse tile
+        for y in 0..zoomed.height / 2 {
+            for x in 0..zoomed.width / 2 {
+                let fine = &zoomed.tiles[2 * x + 2 * y * zoomed.width];
+                let coarse = &world.tiles[20 + x + (15 + y) * world.width];
+                assert_eq!(fine.altitude, coarse.altitude, "({}, {})", x, y);
+                assert_eq!(fine.is_sea(), coarse.is_sea(), "({}, {})", x, y);
+                if fine.biome != Biome::Coast && coarse.biome != Biome::Coast {
+                    assert_eq!(fine.biome, coarse.biome, "({}, {})", x, y);
+                }
+            }
+        }
+    }
+}
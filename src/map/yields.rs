@@ -0,0 +1,63 @@
+use serde::Serialize;
+use crate::map::tile::{Biome, Tile};
+
+/// What a tile produces per turn, in abstract units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Yields {
+    pub food: f64,
+    pub timber: f64,
+    pub stone: f64,
+}
+
+impl Yields {
+    const fn new(food: f64, timber: f64, stone: f64) -> Yields {
+        Yields { food, timber, stone }
+    }
+
+    /// Linear interpolation towards `other`; `t` of 0.0 gives `self`.
+    pub fn lerp(&self, other: &Yields, t: f64) -> Yields {
+        Yields {
+            food: self.food + (other.food - self.food) * t,
+            timber: self.timber + (other.timber - self.timber) * t,
+            stone: self.stone + (other.stone - self.stone) * t,
+        }
+    }
+
+    pub fn add(&self, other: &Yields) -> Yields {
+        Yields::new(self.food + other.food, self.timber + other.timber, self.stone + other.stone)
+    }
+
+    pub fn scale(&self, factor: f64) -> Yields {
+        Yields::new(self.food * factor, self.timber * factor, self.stone * factor)
+    }
+}
+
+impl Biome {
+    pub fn yields(&self) -> Yields {
+        match self {
+            Biome::Frozen =>     Yields::new(0.0, 0.0, 0.5),
+            Biome::Tundra =>     Yields::new(0.5, 0.0, 0.5),
+            Biome::Boreal =>     Yields::new(1.0, 2.0, 0.5),
+            Biome::Temperate =>  Yields::new(2.0, 2.0, 0.5),
+            Biome::Rainforest => Yields::new(1.5, 3.0, 0.0),
+            Biome::Wetland =>    Yields::new(2.0, 1.0, 0.0),
+            Biome::Plains =>     Yields::new(3.0, 0.5, 0.5),
+            Biome::Desert =>     Yields::new(0.0, 0.0, 1.0),
+            Biome::Hill =>       Yields::new(1.0, 0.5, 2.0),
+            Biome::Mountain =>   Yields::new(0.0, 0.0, 3.0),
+            Biome::Peak =>       Yields::new(0.0, 0.0, 1.0),
+            Biome::Coast =>      Yields::new(2.0, 0.5, 0.5),
+            Biome::Sea =>        Yields::new(1.0, 0.0, 0.0),
+            Biome::Debug =>      Yields::default(),
+        }
+    }
+}
+
+impl Tile {
+    /// Yields of the tile's biome, blended with its ecotone, with food scaled
+    /// by fertility left after hazard damage.
+    pub fn yields(&self) -> Yields {
+        let yields = self.biome.yields().lerp(&self.ecotone.yields(), self.blend);
+        Yields { food: yields.food * self.fertility * (1.0 - self.damage), ..yields }
+    }
+}
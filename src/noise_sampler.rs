@@ -18,7 +18,7 @@ pub struct NoiseSampler<'a> {
 }
 
 impl<'a> NoiseSampler<'a> {
-    pub fn new(noise_map: &'a (dyn NoiseFn<f64, 3> + Send + Sync)) -> NoiseSampler {
+    pub fn new(noise_map: &'a (dyn NoiseFn<f64, 3> + Send + Sync)) -> NoiseSampler<'a> {
         NoiseSampler {
             values: Vec::new(),
             noise_map,
@@ -0,0 +1,4 @@
+pub mod sweep;
+pub mod stats;
+pub mod seed_search;
+pub mod overview;
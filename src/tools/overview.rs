@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+use nannou::image::{Rgba, RgbaImage};
+use crate::{
+    image::palette::Palette,
+    map::{
+        chunk::{ChunkedWorld, CHUNK_SIZE},
+        tile::Biome
+    },
+    utils::{cli::Args, helpers::load_from_args}
+};
+
+/// Streams a world of `args.x` by `args.y` tiles through a cache of `cache`
+/// chunks, writing a biome map with one pixel per `downsample` by `downsample`
+/// tiles and printing the biome shares. Memory stays bounded by the cache and
+/// the overview, however large the world.
+pub fn run_overview(args: &Args, output: &str, downsample: usize, cache: usize) -> Result<(), String> {
+    let params = load_from_args(args)?;
+    let palette = Palette::load(&args.palette)?;
+    let downsample = downsample.max(1);
+    let (width, height) = (args.x.div_ceil(downsample), args.y.div_ceil(downsample));
+    let mut sums = vec![[0u32; 4]; width * height];
+    let mut biomes = BTreeMap::<Biome, usize>::new();
+
+    let mut world = ChunkedWorld::new(args, &params, cache)?;
+    let (columns, rows) = (args.x.div_ceil(CHUNK_SIZE), args.y.div_ceil(CHUNK_SIZE));
+    for cy in 0..rows {
+        for cx in 0..columns {
+            for tile in &world.chunk(cx as i64, cy as i64).tiles {
+                let (x, y) = (tile.x as usize, tile.y as usize);
+                if x >= args.x || y >= args.y { continue; }
+                let sum = &mut sums[x / downsample + y / downsample * width];
+                let [r, g, b] = tile.color(&palette);
+                *sum = [sum[0] + r as u32, sum[1] + g as u32, sum[2] + b as u32, sum[3] + 1];
+                *biomes.entry(tile.biome).or_insert(0) += 1;
+            }
+        }
+        println!("[MapGen] Overview row {} of {} done, {} chunks loaded.", cy + 1, rows, world.loaded());
+    }
+
+    let img = RgbaImage::from_fn(width as u32, height as u32, |x, y| {
+        let [r, g, b, count] = sums[x as usize + y as usize * width];
+        let count = count.max(1);
+        Rgba([(r / count) as u8, (g / count) as u8, (b / count) as u8, 255])
+    });
+    img.save(output)
+        .map_err(|e| format!("[MapGen] Could not save overview: {}", e))?;
+
+    let size = (args.x * args.y) as f64;
+    for (biome, count) in &biomes {
+        println!("[MapGen]   {:<12} {:>6.2}%", biome.to_string(), 100.0 * *count as f64 / size);
+    }
+    println!("[MapGen] Overview of {} x {} tiles written to {}", args.x, args.y, output);
+    Ok(())
+}
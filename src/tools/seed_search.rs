@@ -0,0 +1,170 @@
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::{
+    map::{
+        guide::AltitudeGuides,
+        stats::WorldStats,
+        tile::Biome,
+        world::{World, WorldBuilder}
+    },
+    utils::{
+        cli::Args,
+        helpers::{load_from_args, regions},
+        seed::WorldSeed
+    }
+};
+
+/// What makes a map usable. Every criterion is optional.
+#[derive(Deserialize, Default)]
+pub struct SearchCriteria {
+    /// Accepted [min, max] fraction of land tiles
+    #[serde(default)]
+    pub land_ratio: Option<[f64; 2]>,
+    /// Minimum tile count of the largest landmass
+    #[serde(default)]
+    pub min_continent_size: Option<usize>,
+    /// Minimum number of distinct biomes, not counting `Biome::Debug`
+    #[serde(default)]
+    pub min_biomes: Option<usize>,
+    /// Maximum share of tiles left as `Biome::Debug`
+    #[serde(default)]
+    pub max_debug_share: Option<f64>,
+    /// Minimum number of separate river systems. Rivers are not generated
+    /// yet, so any value above 0 is rejected until `generate_rivers` is enabled.
+    #[serde(default)]
+    pub min_rivers: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct SeedScore {
+    pub seed: String,
+    /// Mean of every criterion's score; 1.0 when all are met
+    pub score: f64,
+    pub passes: bool,
+    pub land_ratio: f64,
+    pub largest_continent: usize,
+    pub biomes: usize,
+    pub debug_share: f64,
+    pub rivers: usize,
+}
+
+/// 1.0 inside the range, falling off linearly with the distance relative to the range size.
+fn range_score(value: f64, min: f64, max: f64) -> f64 {
+    let distance = if value < min { min - value } else if value > max { value - max } else { 0.0 };
+    (1.0 - distance / (max - min).abs().max(f64::EPSILON)).max(0.0)
+}
+
+fn at_least(value: f64, min: f64) -> f64 {
+    if min <= 0.0 { 1.0 } else { (value / min).min(1.0) }
+}
+
+fn at_most(value: f64, max: f64) -> f64 {
+    if value <= max { 1.0 } else { (max / value).max(0.0) }
+}
+
+impl SearchCriteria {
+    pub fn parse(data: &str) -> Result<SearchCriteria, String> {
+        let criteria = serde_json::from_str::<SearchCriteria>(data)
+            .map_err(|e| format!("[MapGen] Failed to parse search criteria: {}", e))?;
+        if criteria.min_rivers.is_some_and(|min| min > 0) {
+            return Err("[MapGen] min_rivers can't be met: rivers are not generated yet.".to_string());
+        }
+        Ok(criteria)
+    }
+
+    pub fn score(&self, world: &World) -> SeedScore {
+        let stats = WorldStats::new(world);
+        let largest_continent = stats.largest_landmasses.first().copied().unwrap_or(0);
+        let biomes = stats.biomes.keys().filter(|biome| **biome != Biome::Debug).count();
+        let debug_share = stats.biomes.get(&Biome::Debug).copied().unwrap_or(0.0);
+        let river_tiles = world.rivers.iter().copied().collect::<std::collections::HashSet<usize>>();
+        let rivers = regions(world.width, world.size, |i| river_tiles.contains(&i)).len();
+
+        let scores = [
+            self.land_ratio.map(|[min, max]| range_score(stats.land_ratio, min, max)),
+            self.min_continent_size.map(|min| at_least(largest_continent as f64, min as f64)),
+            self.min_biomes.map(|min| at_least(biomes as f64, min as f64)),
+            self.max_debug_share.map(|max| at_most(debug_share, max)),
+            self.min_rivers.map(|min| at_least(rivers as f64, min as f64)),
+        ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<f64>>();
+
+        SeedScore {
+            seed: world.seed.to_string(),
+            score: if scores.is_empty() { 1.0 } else { scores.iter().sum::<f64>() / scores.len() as f64 },
+            passes: scores.iter().all(|score| *score >= 1.0),
+            land_ratio: stats.land_ratio,
+            largest_continent,
+            biomes,
+            debug_share,
+            rivers,
+        }
+    }
+}
+
+/// Generates `count` worlds and returns the `top` best seeds. Candidates are
+/// derived from `--seed`, or a random seed, and keep every layer override,
+/// so a search can be repeated exactly.
+pub fn run_seed_search(
+    args: &Args,
+    criteria: &str,
+    count: usize,
+    top: usize,
+    output: Option<&String>
+) -> Result<(), String> {
+    let criteria = std::fs::read_to_string(criteria)
+        .map_err(|e| format!("[MapGen] Failed to read search criteria: {}", e))?;
+    let criteria = SearchCriteria::parse(&criteria)?;
+    let params = load_from_args(args)?;
+
+    let guides = Arc::new(AltitudeGuides::load(args, &params)?);
+    let base = WorldSeed::from_args(args)?;
+    println!("[MapGen] Searching seeds derived from \"{}\".", base);
+    let mut results = Vec::<SeedScore>::with_capacity(count);
+    for i in 0..count {
+        let seed = base.candidate(i);
+        println!("[MapGen] Seed search {}/{}: {}", i + 1, count, seed);
+        let world = WorldBuilder::new(args, &params, &guides)?.with_seed(&seed).build();
+        results.push(criteria.score(&world));
+    }
+
+    results.sort_by(|a, b| b.passes.cmp(&a.passes).then(b.score.total_cmp(&a.score)));
+    results.truncate(top);
+
+    println!("[MapGen] Best seeds:");
+    println!("[MapGen]   {:<4} {:<24} {:>6} {:>6} {:>7} {:>10} {:>7} {:>6} {:>7}",
+        "rank", "seed", "score", "pass", "land", "continent", "biomes", "debug", "rivers");
+    for (rank, result) in results.iter().enumerate() {
+        println!("[MapGen]   {:<4} {:<24} {:>6.3} {:>6} {:>6.1}% {:>10} {:>7} {:>5.1}% {:>7}",
+            rank + 1,
+            result.seed,
+            result.score,
+            result.passes,
+            result.land_ratio * 100.0,
+            result.largest_continent,
+            result.biomes,
+            result.debug_share * 100.0,
+            result.rivers
+        );
+    }
+
+    if let Some(output) = output {
+        std::fs::write(output, serde_json::to_string_pretty(&results).unwrap())
+            .map_err(|e| format!("[MapGen] Could not save search results: {}", e))?;
+        println!("[MapGen] Search results written to {}", output);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn river_criteria_are_rejected_until_rivers_exist() {
+        assert!(SearchCriteria::parse(r#"{ "min_rivers": 2 }"#).is_err());
+        assert!(SearchCriteria::parse(r#"{ "min_rivers": 0, "min_biomes": 5 }"#).is_ok());
+    }
+}
@@ -0,0 +1,27 @@
+use std::{path::PathBuf, sync::Arc};
+use crate::{
+    image::{charts::render_stats, palette::Palette},
+    map::{guide::AltitudeGuides, stats::WorldStats, world::WorldBuilder},
+    utils::{cli::Args, helpers::load_from_args}
+};
+
+pub fn run_stats(args: &Args, output: &str) -> Result<(), String> {
+    let params = load_from_args(args)?;
+    let palette = Palette::load(&args.palette)?;
+    let guides = Arc::new(AltitudeGuides::load(args, &params)?);
+    let world = WorldBuilder::new(args, &params, &guides)?.build();
+    let stats = WorldStats::new(&world);
+
+    let output = PathBuf::from(output);
+    std::fs::create_dir_all(&output)
+        .map_err(|e| format!("[MapGen] Failed to create output folder: {}", e))?;
+    let json_file = output.join("stats.json");
+    let image_file = output.join("histogram.png");
+
+    std::fs::write(&json_file, serde_json::to_string_pretty(&stats).unwrap())
+        .map_err(|e| format!("[MapGen] Could not save statistics: {}", e))?;
+    render_stats(&stats, &palette).save(&image_file)
+        .map_err(|e| format!("[MapGen] Could not save histogram: {}", e))?;
+    println!("[MapGen] Statistics written to {} and {}", json_file.display(), image_file.display());
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use std::{collections::BTreeMap, path::PathBuf, str::FromStr, sync::Arc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use nannou::image::{imageops::FilterType, Rgba, RgbaImage, imageops::overlay};
+use crate::{
+    image::{VisualizationMode, overlays::Overlays, palette::Palette, relief::Sun, text::draw_text},
+    map::{guide::AltitudeGuides, world::WorldBuilder},
+    utils::{
+        cli::Args,
+        helpers::{parse_parameters, parameters_path},
+        seed::WorldSeed
+    }
+};
+
+const LABEL_SIZE: f32 = 12.0;
+const LINE_HEIGHT: u32 = 14;
+const PADDING: u32 = 6;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum SweepValues {
+    List(Vec<f64>),
+    Range { from: f64, to: f64, steps: usize },
+}
+
+impl SweepValues {
+    fn values(&self) -> Vec<f64> {
+        match self {
+            SweepValues::List(values) => values.clone(),
+            SweepValues::Range { from, to, steps } => match steps {
+                0 => Vec::new(),
+                1 => vec![*from],
+                n => (0..*n).map(|i| from + (to - from) * i as f64 / (n - 1) as f64).collect()
+            }
+        }
+    }
+}
+
+/// Grid of parameter values to sweep. Parameter names may use dots to reach
+/// nested fields, e.g. `guide.strength`.
+#[derive(Deserialize)]
+pub struct SweepSpec {
+    /// Preset to start from; the parameters file is used when omitted
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Seed strings; every `--layer-seed` is applied on top of each
+    pub seeds: Vec<String>,
+    pub parameters: BTreeMap<String, SweepValues>,
+    #[serde(default = "default_mode")]
+    pub mode: String,
+    #[serde(default = "default_thumbnail_width")]
+    pub thumbnail_width: u32,
+    #[serde(default = "default_columns")]
+    pub columns: usize,
+}
+
+fn default_mode() -> String { "biome".to_string() }
+fn default_thumbnail_width() -> u32 { 240 }
+fn default_columns() -> usize { 4 }
+
+#[derive(Serialize)]
+struct SweepEntry {
+    index: usize,
+    row: usize,
+    column: usize,
+    seed: String,
+    parameters: BTreeMap<String, f64>,
+    error: Option<String>,
+}
+
+/// Largest magnitude below which every whole `f64` is exactly an integer.
+const EXACT_INTEGERS: f64 = 9007199254740992.0;
+
+/// Writes `new` at a dotted path. Whole numbers are written as integers, so
+/// integer fields such as `volcanism.hotspots` can be swept too; float fields
+/// accept them all the same.
+fn set_path(value: &mut Value, path: &str, new: f64) {
+    let target = path.split('.').fold(value, |node, key| {
+        if !node.is_object() { *node = Value::Object(Default::default()); }
+        node.as_object_mut().unwrap().entry(key).or_insert(Value::Null)
+    });
+    *target = if new.fract() == 0.0 && new.abs() < EXACT_INTEGERS { Value::from(new as i64) }
+        else { Value::from(new) };
+}
+
+/// Cartesian product of every parameter axis.
+fn combinations(spec: &SweepSpec) -> Vec<BTreeMap<String, f64>> {
+    spec.parameters.iter().fold(vec![BTreeMap::new()], |combos, (name, values)| {
+        combos.iter()
+            .flat_map(|combo| values.values().into_iter().map(move |value| {
+                let mut combo = combo.clone();
+                combo.insert(name.clone(), value);
+                combo
+            }))
+            .collect()
+    })
+}
+
+pub fn run_sweep(args: &Args, grid: &str, output: &str) -> Result<(), String> {
+    let spec = std::fs::read_to_string(grid)
+        .map_err(|e| format!("[MapGen] Failed to read sweep grid: {}", e))?;
+    let spec = serde_json::from_str::<SweepSpec>(&spec)
+        .map_err(|e| format!("[MapGen] Failed to parse sweep grid: {}", e))?;
+    let mode = VisualizationMode::from_str(&spec.mode)?;
+
+    let base: Value = match &spec.preset {
+        Some(name) => serde_json::json!({ "inherits": name }),
+        None => {
+            let data = std::fs::read_to_string(parameters_path(args))
+                .map_err(|e| format!("[MapGen] Failed to load parameters: {}", e))?;
+            serde_json::from_str(&data)
+                .map_err(|e| format!("[MapGen] Failed to parse parameters: {}", e))?
+        }
+    };
+
+    let combos = combinations(&spec);
+    let total = combos.len() * spec.seeds.len();
+    println!("[MapGen] Sweeping {} combinations over {} seeds ({} worlds).", combos.len(), spec.seeds.len(), total);
+
+    let columns = spec.columns.max(1);
+    let thumb_w = spec.thumbnail_width;
+    let thumb_h = (thumb_w as f64 * args.y as f64 / args.x as f64).round() as u32;
+    let label_lines = spec.parameters.len() as u32 + 1;
+    let cell_w = thumb_w + 2 * PADDING;
+    let cell_h = thumb_h + label_lines * LINE_HEIGHT + 2 * PADDING;
+    let rows = total.div_ceil(columns);
+    let mut sheet = RgbaImage::from_pixel(cell_w * columns as u32, cell_h * rows as u32, Rgba([255, 255, 255, 255]));
+    let mut index = Vec::<SweepEntry>::with_capacity(total);
+
+    let overlays = Overlays {
+        sun: Sun::from_args(args),
+        palette: Palette::load(&args.palette)?,
+        ..Overlays::default()
+    };
+    // Loaded with the first valid cell, then refitted to each cell's parameters
+    let mut guides: Option<Arc<AltitudeGuides>> = None;
+    for seed_text in &spec.seeds {
+        let mut seed = WorldSeed::parse(seed_text)?;
+        seed.set_overrides(&args.layer_seed)?;
+        for combo in &combos {
+            let i = index.len();
+            let (row, column) = (i / columns, i % columns);
+            let (x, y) = (column as u32 * cell_w + PADDING, row as u32 * cell_h + PADDING);
+            println!("[MapGen] Sweep {}/{}: seed {} {:?}", i + 1, total, seed, combo);
+
+            let mut value = base.clone();
+            combo.iter().for_each(|(name, v)| set_path(&mut value, name, *v));
+            let label = format!("sweep cell {}", i);
+            let error = match parse_parameters(value, &label) {
+                Ok(params) => {
+                    let fitted = Arc::new(match &guides {
+                        Some(loaded) => loaded.refit(args, &params)?,
+                        None => AltitudeGuides::load(args, &params)?,
+                    });
+                    let world = WorldBuilder::new(args, &params, &fitted)?.with_seed(&seed).build();
+                    guides = Some(fitted);
+                    let thumb = world.generate_image(&mode, &overlays)
+                        .resize_exact(thumb_w, thumb_h, FilterType::Triangle)
+                        .to_rgba8();
+                    overlay(&mut sheet, &thumb, x, y);
+                    None
+                },
+                Err(report) => {
+                    eprintln!("{}", report);
+                    draw_text(&mut sheet, "invalid parameters", x as i32, (y + thumb_h / 2) as i32, LABEL_SIZE, Rgba([200, 0, 0, 255]));
+                    Some(report)
+                }
+            };
+
+            let lines = std::iter::once(format!("#{} seed {}", i, seed))
+                .chain(combo.iter().map(|(name, v)| format!("{} = {:.3}", name, v)));
+            for (line, text) in lines.enumerate() {
+                let ty = y + thumb_h + 2 + line as u32 * LINE_HEIGHT;
+                draw_text(&mut sheet, &text, x as i32, ty as i32, LABEL_SIZE, Rgba([0, 0, 0, 255]));
+            }
+
+            index.push(SweepEntry { index: i, row, column, seed: seed.to_string(), parameters: combo.clone(), error });
+        }
+    }
+
+    let output = PathBuf::from(output);
+    std::fs::create_dir_all(&output)
+        .map_err(|e| format!("[MapGen] Failed to create output folder: {}", e))?;
+    let sheet_file = output.join("sheet.png");
+    let index_file = output.join("index.json");
+    sheet.save(&sheet_file)
+        .map_err(|e| format!("[MapGen] Could not save contact sheet: {}", e))?;
+    std::fs::write(&index_file, serde_json::to_string_pretty(&index).unwrap())
+        .map_err(|e| format!("[MapGen] Could not save sweep index: {}", e))?;
+    println!("[MapGen] Contact sheet written to {} with index {}", sheet_file.display(), index_file.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn integer_fields_can_be_swept() {
+        let mut value = json!({ "inherits": "earthlike" });
+        set_path(&mut value, "noise.altitude.octaves", 3.0);
+        set_path(&mut value, "volcanism.hotspots", 5.0);
+        set_path(&mut value, "sea_level", 0.0);
+        set_path(&mut value, "hills_h", 0.6);
+        let params = parse_parameters(value, "sweep cell 0").unwrap();
+
+        assert_eq!(params.noise.altitude.octaves, 3);
+        assert_eq!(params.volcanism.unwrap().hotspots, 5);
+        assert_eq!((params.sea_level, params.hills_h), (0.0, 0.6));
+    }
+}
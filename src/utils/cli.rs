@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -12,9 +12,112 @@ pub struct Args {
     #[arg(short, long, default_value_t = false)]
     pub debug: bool,
 
+    /// Parameters file to load and watch instead of the default one
     #[arg(short, long)]
     pub file: Option<String>,
 
+    /// Master seed: a number, any text, or a full seed string printed by a previous run
     #[arg(short, long)]
-    pub seeds: Option<Vec<u32>>,
+    pub seed: Option<String>,
+
+    /// Overrides a single noise layer, e.g. `altitude=1234`
+    #[arg(long)]
+    pub layer_seed: Vec<String>,
+
+    /// Use a bundled parameter preset instead of the parameters file
+    #[arg(short, long)]
+    pub preset: Option<String>,
+
+    /// Grayscale PNG (8 or 16 bit) used as the altitude source
+    #[arg(long)]
+    pub heightmap: Option<String>,
+
+    /// How much of the altitude comes from the heightmap (0.0 - 1.0)
+    #[arg(long, default_value_t = 1.0)]
+    pub heightmap_blend: f64,
+
+    /// Direction of the light for hillshading, in degrees clockwise from north
+    #[arg(long, default_value_t = 315.0)]
+    pub sun_azimuth: f64,
+
+    /// Height of the light for hillshading, in degrees above the horizon
+    #[arg(long, default_value_t = 45.0)]
+    pub sun_elevation: f64,
+
+    /// Vertical exaggeration of hillshaded terrain
+    #[arg(long, default_value_t = 1.0)]
+    pub exaggeration: f64,
+
+    /// Contour lines to draw, e.g. `altitude=0.1` or `temperature=0.05`; repeatable
+    #[arg(long)]
+    pub contour: Vec<String>,
+
+    /// Bundled palette name (classic, perceptual, atlas) or path to a JSON palette file
+    #[arg(long, default_value = "classic")]
+    pub palette: String,
+
+    /// Pixels per tile in saved images
+    #[arg(long, default_value_t = 1)]
+    pub export_scale: u32,
+
+    /// Draw a legend, the seed, a scale bar and a compass on saved images
+    #[arg(long, default_value_t = false)]
+    pub annotate: bool,
+
+    /// Run a headless tool instead of the viewer
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Generate every combination of a parameter grid and write a labeled contact sheet
+    Sweep {
+        /// JSON file listing seeds and parameter values
+        grid: String,
+
+        /// Folder for the contact sheet and its index
+        #[arg(short, long, default_value = "sweep")]
+        output: String,
+    },
+
+    /// Generate a world and write its statistics as JSON and as a histogram image
+    Stats {
+        /// Folder for stats.json and histogram.png
+        #[arg(short, long, default_value = "stats")]
+        output: String,
+    },
+
+    /// Generate many worlds and rank their seeds against quality criteria
+    SeedSearch {
+        /// JSON file with the criteria to score worlds against
+        criteria: String,
+
+        /// How many worlds to generate
+        #[arg(short, long, default_value_t = 50)]
+        count: usize,
+
+        /// How many of the best seeds to report
+        #[arg(short, long, default_value_t = 10)]
+        top: usize,
+
+        /// JSON file for the ranked results
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Stream a world of any size through a chunk cache and write a downsampled biome map
+    Overview {
+        /// Image file for the overview
+        #[arg(short, long, default_value = "overview.png")]
+        output: String,
+
+        /// Tiles per overview pixel along each side
+        #[arg(short, long, default_value_t = 16)]
+        downsample: usize,
+
+        /// Chunks kept in memory at once
+        #[arg(short, long, default_value_t = 64)]
+        cache: usize,
+    },
 }
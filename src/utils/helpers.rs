@@ -1,6 +1,11 @@
 use std::{path::PathBuf, str::FromStr};
 use nannou::math::map_range;
-use crate::WorldParameters;
+use serde_json::Value;
+use crate::{
+    WorldParameters,
+    map::{presets::{resolve, load_preset}, validation::{validate, report}},
+    utils::cli::Args
+};
 
 /// Scales a f64 within [-1.0, 1.0] to a u8 within [0, 255]
 /// No error handling!
@@ -13,13 +18,57 @@ pub fn adjacent(i: usize, width: usize, world_size: usize) -> Vec<usize> {
     else if i == width - 1                   { vec!(i-1, i+width)               } // last tile of first row
     else if i == world_size - 1              { vec!(i-1, i-width)               } // last tile
     else if i == world_size - width          { vec!(i+1, i-width)               } // first tile of last row
-    else if i % width == 0                   { vec!(i+1, i-width, i+width)      } // first tile of row
+    else if i.is_multiple_of(width)          { vec!(i+1, i-width, i+width)      } // first tile of row
     else if i % width == width - 1           { vec!(i-1, i-width, i+width)      } // last tile of row
     else if i < width                        { vec!(i-1, i+1, i+width)          } // first row
     else if i > world_size - width           { vec!(i-1, i+1, i-width)          } // last row
     else                                     { vec!(i-1, i+1, i-width, i+width) } // elsewhere
 }
 
+/// The up to eight tiles surrounding `i`, without wrapping around the map edges.
+pub fn neighbors8(i: usize, width: usize, world_size: usize) -> Vec<usize> {
+    let (x, y) = ((i % width) as i64, (i / width) as i64);
+    let height = (world_size / width) as i64;
+    let mut neighbors = Vec::with_capacity(8);
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let (nx, ny) = (x + dx, y + dy);
+            if (dx, dy) != (0, 0) && nx >= 0 && ny >= 0 && nx < width as i64 && ny < height {
+                neighbors.push(nx as usize + ny as usize * width);
+            }
+        }
+    }
+    neighbors
+}
+
+/// Groups the tiles matching `predicate` into 4-connected regions.
+pub fn regions(
+    width: usize,
+    world_size: usize,
+    predicate: impl Fn(usize) -> bool
+) -> Vec<Vec<usize>> {
+    let mut visited = vec![false; world_size];
+    let mut regions = Vec::new();
+
+    for start in 0..world_size {
+        if visited[start] || !predicate(start) { continue; }
+        visited[start] = true;
+        let mut region = Vec::new();
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            region.push(i);
+            for neighbor in adjacent(i, width, world_size) {
+                if !visited[neighbor] && predicate(neighbor) {
+                    visited[neighbor] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+        regions.push(region);
+    }
+    regions
+}
+
 pub fn xy_to_index(tile: &crate::map::tile::Tile, width: usize) -> usize {
     (tile.x as usize) + ((tile.y as usize) * width)
 }
@@ -30,11 +79,52 @@ pub fn index_to_xy(index: usize, width: usize) -> (f32, f32) {
     (x, y)
 }
 
-pub fn load_parameters() -> WorldParameters {
-    let file = PathBuf::from_str("/home/tsrodr/Run/civ-sim/src/parameters.json")
+/// Linearly interpolated quantile of an ascending slice; `q` within [0.0, 1.0]
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let (low, high) = (position.floor() as usize, position.ceil() as usize);
+    sorted[low] + (sorted[high] - sorted[low]) * (position - low as f64)
+}
+
+/// Resolves presets, parses and validates a parameters object. Warnings are
+/// printed; any error refuses the whole set with a report of every issue.
+pub fn parse_parameters(value: Value, source: &str) -> Result<WorldParameters, String> {
+    let value = resolve(value, &mut Vec::new())
+        .map_err(|e| format!("[MapGen] Failed to resolve {}: {}", source, e))?;
+    let params = serde_json::from_value::<WorldParameters>(value.clone())
+        .map_err(|e| format!("[MapGen] Failed to parse {}: {}", source, e))?;
+
+    match validate(&params, &value) {
+        Ok(warnings) => {
+            if !warnings.is_empty() {
+                println!("[MapGen] Warnings in {}:\n{}", source, report(&warnings));
+            }
+            Ok(params)
+        },
+        Err(issues) => Err(format!("[MapGen] Invalid {}:\n{}", source, report(&issues)))
+    }
+}
+
+pub const PARAMETERS_FILE: &str = "/home/tsrodr/Run/civ-sim/src/parameters.json";
+
+pub fn load_parameters(path: &str) -> Result<WorldParameters, String> {
+    let file = PathBuf::from_str(path)
         .expect("[MapGen] Failed to load json file.");
     let data = std::fs::read_to_string(file)
-        .expect("[MapGen] Failed to load parameters.");
-    serde_json::from_str::<WorldParameters>(&data)
-        .expect("[MapGen] Failed to parse parameters.")
+        .map_err(|e| format!("[MapGen] Failed to load parameters: {}", e))?;
+    let value = serde_json::from_str::<Value>(&data)
+        .map_err(|e| format!("[MapGen] Failed to parse parameters: {}", e))?;
+    parse_parameters(value, "parameters file")
+}
+
+pub fn parameters_path(args: &Args) -> String {
+    args.file.clone().unwrap_or(PARAMETERS_FILE.to_string())
+}
+
+/// Loads the preset named on the command line, or the parameters file otherwise.
+pub fn load_from_args(args: &Args) -> Result<WorldParameters, String> {
+    match &args.preset {
+        Some(name) => load_preset(name),
+        None => load_parameters(&parameters_path(args))
+    }
 }
@@ -1,2 +1,4 @@
 pub mod cli;
 pub mod helpers;
+pub mod seed;
+pub mod watch;
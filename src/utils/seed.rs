@@ -0,0 +1,211 @@
+use std::fmt::{Display, Formatter, Result};
+use rand::Rng;
+use crate::utils::cli::Args;
+
+/// Noise layers in the order `Tile::new` indexes them.
+pub const LAYERS: [&str; 4] = ["temperature", "altitude", "rainfall", "detail"];
+
+/// A master seed plus optional per-layer overrides. Its string form,
+/// e.g. `atlantis;altitude=1234`, reproduces the exact same layer seeds.
+#[derive(Clone, Debug)]
+pub struct WorldSeed {
+    pub master: String,
+    pub overrides: Vec<(usize, u32)>,
+}
+
+/// FNV-1a, used instead of std's hasher because its output must never change.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn parse_layer(text: &str) -> std::result::Result<(usize, u32), String> {
+    let (name, value) = text.split_once('=')
+        .ok_or(format!("Layer seed '{}' should look like 'altitude=1234'.", text))?;
+    let layer = LAYERS.iter()
+        .position(|layer| *layer == name.trim())
+        .ok_or(format!("Unknown layer '{}'; expected one of {}.", name, LAYERS.join(", ")))?;
+    let value = value.trim();
+    let seed = value.parse::<u32>().unwrap_or(fnv1a(value) as u32);
+    Ok((layer, seed))
+}
+
+impl WorldSeed {
+    pub fn random() -> WorldSeed {
+        WorldSeed {
+            master: rand::thread_rng().gen::<u64>().to_string(),
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Parses a seed string: a number or any text, optionally followed by
+    /// `;layer=value` overrides. Numbers are taken by value, so `012` and `12`
+    /// are the same seed. `;` always starts an override and cannot be part of
+    /// a text seed.
+    pub fn parse(text: &str) -> std::result::Result<WorldSeed, String> {
+        let mut parts = text.split(';');
+        let master = parts.next().unwrap_or_default().trim();
+        let master = master.parse::<u64>().map_or(master.to_string(), |value| value.to_string());
+        let mut seed = WorldSeed { master, overrides: Vec::new() };
+        for part in parts.filter(|part| !part.trim().is_empty()) {
+            if !part.contains('=') {
+                return Err(format!(
+                    "'{}' in seed '{}' is not a layer override; text seeds cannot contain ';'.",
+                    part, text
+                ));
+            }
+            seed.set_override(part)?;
+        }
+        Ok(seed)
+    }
+
+    /// The seed asked for on the command line: `--seed`, or a random one,
+    /// with every `--layer-seed` applied on top.
+    pub fn from_args(args: &Args) -> std::result::Result<WorldSeed, String> {
+        let mut seed = match &args.seed {
+            None => WorldSeed::random(),
+            Some(text) => WorldSeed::parse(text).map_err(|e| format!("[MapGen] Failed to parse seed: {}", e))?,
+        };
+        seed.set_overrides(&args.layer_seed)
+            .map_err(|e| format!("[MapGen] Failed to parse layer seed: {}", e))?;
+        Ok(seed)
+    }
+
+    /// The `index`-th seed of a search starting from this one. Its master is
+    /// derived from this master's value and it keeps the layer overrides.
+    pub fn candidate(&self, index: usize) -> WorldSeed {
+        WorldSeed {
+            master: self.derive(&format!("candidate {}", index)).to_string(),
+            overrides: self.overrides.clone(),
+        }
+    }
+
+    pub fn set_overrides(&mut self, layers: &[String]) -> std::result::Result<(), String> {
+        layers.iter().try_for_each(|layer| self.set_override(layer))
+    }
+
+    pub fn set_override(&mut self, text: &str) -> std::result::Result<(), String> {
+        let (layer, value) = parse_layer(text)?;
+        self.overrides.retain(|(l, _)| *l != layer);
+        self.overrides.push((layer, value));
+        self.overrides.sort();
+        Ok(())
+    }
+
+    pub fn master_value(&self) -> u64 {
+        self.master.parse::<u64>().unwrap_or(fnv1a(&self.master))
+    }
+
+    /// Independent seed for any named subsystem.
+    pub fn derive(&self, label: &str) -> u64 {
+        splitmix64(self.master_value() ^ fnv1a(label))
+    }
+
+    pub fn layers(&self) -> [u32; 4] {
+        let mut seeds = LAYERS.map(|layer| self.derive(layer) as u32);
+        self.overrides.iter().for_each(|(layer, value)| seeds[*layer] = *value);
+        seeds
+    }
+}
+
+impl Display for WorldSeed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.master)?;
+        for (layer, value) in &self.overrides {
+            write!(f, ";{}={}", LAYERS[*layer], value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_matches_reference_vectors() {
+        assert_eq!(fnv1a(""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a("foobar"), 0x85944171f73967e8);
+    }
+
+    /// Shared seed strings must keep producing the same worlds, so these
+    /// values may never change.
+    #[test]
+    fn derived_seeds_are_pinned() {
+        let numeric = WorldSeed::parse("12").unwrap();
+        assert_eq!(numeric.master_value(), 12);
+        assert_eq!(numeric.layers(), [64837425, 861791214, 3401389205, 2193102970]);
+        assert_eq!(numeric.derive("volcanism"), 3558158647272834749);
+
+        let text = WorldSeed::parse("atlantis").unwrap();
+        assert_eq!(text.master_value(), 10166093895567885693);
+        assert_eq!(text.layers(), [3801967760, 756449697, 1907043386, 1746185387]);
+        assert_eq!(text.derive("volcanism"), 6671528950420804244);
+    }
+
+    #[test]
+    fn overrides_replace_single_layers() {
+        let seed = WorldSeed::parse("atlantis;altitude=1234").unwrap();
+        assert_eq!(seed.layers(), [3801967760, 1234, 1907043386, 1746185387]);
+        assert_eq!(seed.derive("volcanism"), WorldSeed::parse("atlantis").unwrap().derive("volcanism"));
+
+        let mut text = WorldSeed::parse("atlantis;rainfall=7").unwrap();
+        text.set_override("rainfall=dunes").unwrap();
+        assert_eq!(text.layers()[2], fnv1a("dunes") as u32);
+    }
+
+    #[test]
+    fn seed_strings_round_trip() {
+        for text in ["12", "atlantis", "atlantis;altitude=1234;detail=5", "two words"] {
+            let seed = WorldSeed::parse(text).unwrap();
+            let reparsed = WorldSeed::parse(&seed.to_string()).unwrap();
+            assert_eq!(seed.to_string(), text);
+            assert_eq!(reparsed.layers(), seed.layers());
+        }
+    }
+
+    #[test]
+    fn numeric_seeds_are_taken_by_value() {
+        let (padded, plain) = (WorldSeed::parse("012").unwrap(), WorldSeed::parse("12").unwrap());
+        assert_eq!(padded.to_string(), "12");
+        assert_eq!(padded.layers(), plain.layers());
+    }
+
+    #[test]
+    fn candidates_keep_layer_overrides() {
+        let mut base = WorldSeed::parse("atlantis;altitude=1234").unwrap();
+        base.set_overrides(&["detail=5".to_string()]).unwrap();
+        let (first, second) = (base.candidate(0), base.candidate(1));
+
+        assert_eq!(first.to_string(), format!("{};altitude=1234;detail=5", first.master));
+        assert_eq!(first.master_value(), base.derive("candidate 0"));
+        assert_ne!(first.master, second.master);
+        assert_eq!([first.layers()[1], first.layers()[3]], [1234, 5]);
+        assert_eq!(WorldSeed::parse(&first.to_string()).unwrap().layers(), first.layers());
+    }
+
+    #[test]
+    fn bad_command_line_seeds_are_errors() {
+        use clap::Parser;
+        let args = Args::parse_from(["civ-sim", "-s", "a;b"]);
+        assert!(WorldSeed::from_args(&args).unwrap_err().starts_with("[MapGen] Failed to parse seed"));
+        let args = Args::parse_from(["civ-sim", "-s", "a", "--layer-seed", "volcano=1"]);
+        assert!(WorldSeed::from_args(&args).unwrap_err().starts_with("[MapGen] Failed to parse layer seed"));
+    }
+
+    #[test]
+    fn semicolons_in_text_seeds_are_rejected() {
+        assert!(WorldSeed::parse("salt;pepper").is_err());
+        assert!(WorldSeed::parse("salt;volcano=1").is_err());
+        assert!(WorldSeed::parse("salt;").is_ok());
+    }
+}
@@ -0,0 +1,43 @@
+use std::{
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::{Duration, SystemTime}
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Polls a file's modification time on a background thread.
+pub struct FileWatcher {
+    rx: Receiver<()>,
+}
+
+impl FileWatcher {
+    /// `notify` runs on the watcher thread after every change, e.g. to wake the app up.
+    pub fn spawn(path: PathBuf, notify: impl Fn() + Send + 'static) -> FileWatcher {
+        let (tx, rx) = channel::<()>();
+        let modified = |path: &PathBuf| -> Option<SystemTime> {
+            std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+        };
+
+        thread::spawn(move || {
+            let mut last = modified(&path);
+            loop {
+                thread::sleep(POLL_INTERVAL);
+                let current = modified(&path);
+                if current != last {
+                    last = current;
+                    if tx.send(()).is_err() { break; }
+                    notify();
+                }
+            }
+        });
+
+        FileWatcher { rx }
+    }
+
+    /// Whether the file changed since the last call.
+    pub fn changed(&self) -> bool {
+        self.rx.try_iter().count() > 0
+    }
+}